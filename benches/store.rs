@@ -0,0 +1,168 @@
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+use url::Url;
+use web_crawler_rs::{
+    bloom_store::BloomStore,
+    sharded_store::ShardedStore,
+    store::{Store, VisitedStore},
+};
+
+/// Tracks the number of bytes currently allocated, so the memory use of each store can be
+/// reported alongside its throughput.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const NUM_URLS: usize = 100_000;
+
+fn build_urls() -> Vec<Url> {
+    (0..NUM_URLS)
+        .map(|i| {
+            Url::parse(&format!(
+                "https://example.com/section-{}/page-{}",
+                i % 100,
+                i
+            ))
+            .unwrap()
+        })
+        .collect()
+}
+
+/// Report the bytes held by a store after inserting every URL.
+fn report_memory<S: VisitedStore<Url>>(name: &str, urls: &[Url], build: impl Fn() -> S) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    let store = build();
+    for url in urls {
+        store.insert(url.clone());
+    }
+    let after = ALLOCATED.load(Ordering::Relaxed);
+    println!(
+        "{name}: {} KiB for {} URLs",
+        after.saturating_sub(before) / 1024,
+        urls.len()
+    );
+    drop(store);
+}
+
+fn insert(c: &mut Criterion) {
+    let urls = build_urls();
+    report_memory("Store", &urls, Store::<Url>::new);
+    report_memory("ShardedStore", &urls, ShardedStore::<Url>::default);
+    report_memory("BloomStore (1%)", &urls, || BloomStore::new(NUM_URLS, 0.01));
+    report_memory("BloomStore (0.1%)", &urls, || {
+        BloomStore::new(NUM_URLS, 0.001)
+    });
+
+    let mut group = c.benchmark_group("insert");
+    group.throughput(Throughput::Elements(NUM_URLS as u64));
+    group.sample_size(10);
+    group.bench_function("Store", |b| {
+        b.iter_batched(
+            || (Store::<Url>::new(), urls.clone()),
+            |(store, urls)| {
+                for url in urls {
+                    black_box(store.insert(url));
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("ShardedStore", |b| {
+        b.iter_batched(
+            || (ShardedStore::<Url>::default(), urls.clone()),
+            |(store, urls)| {
+                for url in urls {
+                    black_box(store.insert(url));
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("BloomStore", |b| {
+        b.iter_batched(
+            || (BloomStore::new(NUM_URLS, 0.01), urls.clone()),
+            |(store, urls)| {
+                for url in urls {
+                    black_box(store.insert(url));
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+/// Insert every URL, split evenly across the given number of threads.
+fn insert_concurrently<S: VisitedStore<Url>>(store: &S, urls: &[Url], threads: usize) {
+    thread::scope(|scope| {
+        for chunk in urls.chunks(urls.len().div_ceil(threads)) {
+            scope.spawn(move || {
+                for url in chunk {
+                    black_box(store.insert(url.clone()));
+                }
+            });
+        }
+    });
+}
+
+fn concurrent_insert(c: &mut Criterion) {
+    let urls = build_urls();
+    let mut group = c.benchmark_group("concurrent_insert");
+    group.throughput(Throughput::Elements(NUM_URLS as u64));
+    group.sample_size(10);
+    for threads in [1, 2, 4, 8] {
+        group.bench_with_input(BenchmarkId::new("Store", threads), &threads, |b, &t| {
+            b.iter_batched(
+                Store::<Url>::new,
+                |store| insert_concurrently(&store, &urls, t),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(
+            BenchmarkId::new("ShardedStore", threads),
+            &threads,
+            |b, &t| {
+                b.iter_batched(
+                    ShardedStore::<Url>::default,
+                    |store| insert_concurrently(&store, &urls, t),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("BloomStore", threads),
+            &threads,
+            |b, &t| {
+                b.iter_batched(
+                    || BloomStore::<Url>::new(NUM_URLS, 0.01),
+                    |store| insert_concurrently(&store, &urls, t),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, insert, concurrent_insert);
+criterion_main!(benches);
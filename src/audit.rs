@@ -0,0 +1,377 @@
+use crate::{
+    html_downloader::HtmlDownloader,
+    html_parser::HtmlParser,
+    results::{CrawlResults, PageResult},
+};
+use reqwest::StatusCode;
+use std::{collections::HashMap, fmt};
+use url::Url;
+
+/// A problem with the canonical or hreflang links of a page.
+#[derive(Clone, Debug, PartialEq)]
+pub enum AuditIssue {
+    /// The canonical URL could not be fetched.
+    CanonicalUnreachable { page: Url, canonical: Url },
+    /// The canonical URL does not return a success status.
+    CanonicalNotOk {
+        page: Url,
+        canonical: Url,
+        status: StatusCode,
+    },
+    /// The canonical URL redirects to another page.
+    CanonicalRedirects {
+        page: Url,
+        canonical: Url,
+        target: Url,
+    },
+    /// The canonical URL declares a different canonical URL of its own.
+    CanonicalChain {
+        page: Url,
+        canonical: Url,
+        next: Url,
+    },
+    /// An alternate language version could not be fetched.
+    HreflangUnreachable {
+        page: Url,
+        lang: String,
+        alternate: Url,
+    },
+    /// An alternate language version does not return a success status.
+    HreflangNotOk {
+        page: Url,
+        lang: String,
+        alternate: Url,
+        status: StatusCode,
+    },
+    /// An alternate language version redirects to another page.
+    HreflangRedirects {
+        page: Url,
+        lang: String,
+        alternate: Url,
+        target: Url,
+    },
+    /// An alternate language version does not link back to the page.
+    HreflangNoReturn {
+        page: Url,
+        lang: String,
+        alternate: Url,
+    },
+}
+
+impl fmt::Display for AuditIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AuditIssue::CanonicalUnreachable { page, canonical } => {
+                write!(f, "{page}: canonical {canonical} could not be fetched")
+            }
+            AuditIssue::CanonicalNotOk {
+                page,
+                canonical,
+                status,
+            } => write!(f, "{page}: canonical {canonical} returned {status}"),
+            AuditIssue::CanonicalRedirects {
+                page,
+                canonical,
+                target,
+            } => write!(f, "{page}: canonical {canonical} redirects to {target}"),
+            AuditIssue::CanonicalChain {
+                page,
+                canonical,
+                next,
+            } => write!(
+                f,
+                "{page}: canonical {canonical} is canonicalised to {next}"
+            ),
+            AuditIssue::HreflangUnreachable {
+                page,
+                lang,
+                alternate,
+            } => write!(
+                f,
+                "{page}: hreflang {lang} alternate {alternate} could not be fetched"
+            ),
+            AuditIssue::HreflangNotOk {
+                page,
+                lang,
+                alternate,
+                status,
+            } => write!(
+                f,
+                "{page}: hreflang {lang} alternate {alternate} returned {status}"
+            ),
+            AuditIssue::HreflangRedirects {
+                page,
+                lang,
+                alternate,
+                target,
+            } => write!(
+                f,
+                "{page}: hreflang {lang} alternate {alternate} redirects to {target}"
+            ),
+            AuditIssue::HreflangNoReturn {
+                page,
+                lang,
+                alternate,
+            } => write!(
+                f,
+                "{page}: hreflang {lang} alternate {alternate} does not link back"
+            ),
+        }
+    }
+}
+
+/// Checks the canonical and hreflang links of crawled pages for consistency. Targets which
+/// were not visited during the crawl are fetched on demand.
+pub struct Auditor<'a> {
+    results: &'a CrawlResults,
+    html_downloader: &'a HtmlDownloader,
+    fetched: HashMap<Url, Option<PageResult>>,
+}
+
+impl<'a> Auditor<'a> {
+    pub fn new(results: &'a CrawlResults, html_downloader: &'a HtmlDownloader) -> Self {
+        Auditor {
+            results,
+            html_downloader,
+            fetched: HashMap::new(),
+        }
+    }
+
+    /// Audit every successfully fetched page, returning the issues found.
+    pub async fn audit(mut self) -> Vec<AuditIssue> {
+        let mut pages: Vec<PageResult> = self
+            .results
+            .pages()
+            .into_iter()
+            .filter(PageResult::is_success)
+            .collect();
+        pages.sort_by(|a, b| a.url.cmp(&b.url));
+
+        let mut issues = Vec::new();
+        for page in pages {
+            issues.extend(self.audit_canonical(&page).await);
+            issues.extend(self.audit_hreflang(&page).await);
+        }
+        issues
+    }
+
+    async fn audit_canonical(&mut self, page: &PageResult) -> Option<AuditIssue> {
+        let canonical = page.canonical.clone().filter(|c| *c != page.url)?;
+        let target = self.lookup(&canonical).await;
+        let Some((status, target)) = target.and_then(|target| Some((target.status?, target)))
+        else {
+            return Some(AuditIssue::CanonicalUnreachable {
+                page: page.url.clone(),
+                canonical,
+            });
+        };
+
+        if !status.is_success() {
+            Some(AuditIssue::CanonicalNotOk {
+                page: page.url.clone(),
+                canonical,
+                status,
+            })
+        } else if target.redirected() {
+            Some(AuditIssue::CanonicalRedirects {
+                page: page.url.clone(),
+                canonical,
+                target: target.final_url,
+            })
+        } else {
+            let next = target.canonical.filter(|next| *next != canonical)?;
+            Some(AuditIssue::CanonicalChain {
+                page: page.url.clone(),
+                canonical,
+                next,
+            })
+        }
+    }
+
+    async fn audit_hreflang(&mut self, page: &PageResult) -> Vec<AuditIssue> {
+        let mut issues = Vec::new();
+        for (lang, alternate) in &page.hreflang {
+            if *alternate == page.url {
+                continue;
+            }
+            let target = self.lookup(alternate).await;
+            let Some((status, target)) = target.and_then(|target| Some((target.status?, target)))
+            else {
+                issues.push(AuditIssue::HreflangUnreachable {
+                    page: page.url.clone(),
+                    lang: lang.clone(),
+                    alternate: alternate.clone(),
+                });
+                continue;
+            };
+
+            if !status.is_success() {
+                issues.push(AuditIssue::HreflangNotOk {
+                    page: page.url.clone(),
+                    lang: lang.clone(),
+                    alternate: alternate.clone(),
+                    status,
+                });
+            } else if target.redirected() {
+                issues.push(AuditIssue::HreflangRedirects {
+                    page: page.url.clone(),
+                    lang: lang.clone(),
+                    alternate: alternate.clone(),
+                    target: target.final_url,
+                });
+            } else if !target.hreflang.values().any(|u| *u == page.url) {
+                issues.push(AuditIssue::HreflangNoReturn {
+                    page: page.url.clone(),
+                    lang: lang.clone(),
+                    alternate: alternate.clone(),
+                });
+            }
+        }
+        issues
+    }
+
+    /// Find the result for a URL, fetching and parsing it if it wasn't visited by the crawl.
+    async fn lookup(&mut self, url: &Url) -> Option<PageResult> {
+        if let Some(page) = self.results.get(url) {
+            return Some(page);
+        }
+        if let Some(page) = self.fetched.get(url) {
+            return page.clone();
+        }
+
+        let page = match self.html_downloader.fetch(url.clone()).await {
+            Ok(html_page) => {
+                let mut page =
+                    PageResult::new(url.clone(), html_page.url.clone(), html_page.status);
+                if html_page.status.is_success() {
+                    let parsed = HtmlParser::default().parse(url, &html_page.url, html_page.body);
+                    page.canonical = parsed.canonical;
+                    page.hreflang = parsed.hreflang;
+                }
+                Some(page)
+            }
+            Err(_) => None,
+        };
+        self.fetched.insert(url.clone(), page.clone());
+        page
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AuditIssue, Auditor};
+    use crate::{
+        html_downloader::HtmlDownloader,
+        results::{CrawlResults, PageResult},
+    };
+    use reqwest::StatusCode;
+    use url::Url;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn page(url: &Url, canonical: Option<&Url>) -> PageResult {
+        let mut page = PageResult::new(url.clone(), url.clone(), StatusCode::OK);
+        page.canonical = canonical.cloned();
+        page
+    }
+
+    #[tokio::test]
+    async fn audit_canonical() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/missing"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&mock_server)
+            .await;
+        let base = Url::parse(&mock_server.uri()).unwrap();
+        let [a, b, c, missing] = ["a", "b", "c", "missing"].map(|p| base.join(p).unwrap());
+
+        // a -> b -> c is a chain, c is canonical, missing was never crawled and returns 404.
+        let results = CrawlResults::new();
+        results.insert(page(&a, Some(&b)));
+        results.insert(page(&b, Some(&c)));
+        results.insert(page(&c, Some(&c)));
+        let orphan = base.join("orphan").unwrap();
+        results.insert(page(&orphan, Some(&missing)));
+
+        let downloader = HtmlDownloader::default();
+        let issues = Auditor::new(&results, &downloader).audit().await;
+        assert_eq!(
+            issues,
+            vec![
+                AuditIssue::CanonicalChain {
+                    page: a,
+                    canonical: b,
+                    next: c
+                },
+                AuditIssue::CanonicalNotOk {
+                    page: orphan,
+                    canonical: missing,
+                    status: StatusCode::NOT_FOUND
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn audit_hreflang() {
+        let en = Url::parse("https://example.com/en/").unwrap();
+        let fr = Url::parse("https://example.com/fr/").unwrap();
+        let de = Url::parse("https://example.com/de/").unwrap();
+        let [es, it, pt] = ["es", "it", "pt"].map(|lang| en.join(&format!("/{lang}/")).unwrap());
+        let es_home = es.join("home").unwrap();
+
+        // en lists fr and de, only fr links back. es redirects, it is missing and pt couldn't
+        // be fetched.
+        let mut en_page = page(&en, None);
+        en_page.hreflang.insert("fr".to_string(), fr.clone());
+        en_page.hreflang.insert("de".to_string(), de.clone());
+        en_page.hreflang.insert("es".to_string(), es.clone());
+        en_page.hreflang.insert("it".to_string(), it.clone());
+        en_page.hreflang.insert("pt".to_string(), pt.clone());
+        let mut fr_page = page(&fr, None);
+        fr_page.hreflang.insert("en".to_string(), en.clone());
+
+        let results = CrawlResults::new();
+        results.insert(en_page);
+        results.insert(fr_page);
+        results.insert(page(&de, None));
+        results.insert(PageResult::new(es.clone(), es_home.clone(), StatusCode::OK));
+        results.insert(PageResult::new(
+            it.clone(),
+            it.clone(),
+            StatusCode::NOT_FOUND,
+        ));
+        results.insert(PageResult::failed(pt.clone(), "timed out".to_string()));
+
+        let downloader = HtmlDownloader::default();
+        let issues = Auditor::new(&results, &downloader).audit().await;
+        assert_eq!(
+            issues,
+            vec![
+                AuditIssue::HreflangNoReturn {
+                    page: en.clone(),
+                    lang: "de".to_string(),
+                    alternate: de
+                },
+                AuditIssue::HreflangRedirects {
+                    page: en.clone(),
+                    lang: "es".to_string(),
+                    alternate: es,
+                    target: es_home
+                },
+                AuditIssue::HreflangNotOk {
+                    page: en.clone(),
+                    lang: "it".to_string(),
+                    alternate: it,
+                    status: StatusCode::NOT_FOUND
+                },
+                AuditIssue::HreflangUnreachable {
+                    page: en,
+                    lang: "pt".to_string(),
+                    alternate: pt
+                },
+            ]
+        );
+    }
+}
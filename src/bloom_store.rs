@@ -0,0 +1,114 @@
+use crate::store::VisitedStore;
+use std::{
+    hash::{BuildHasher, Hash, RandomState},
+    marker::PhantomData,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// A probabilistic store backed by a Bloom filter. Memory use is fixed by the expected number
+/// of items and the false positive rate rather than the size of the items, and inserts never
+/// take a lock. The trade-off is that a small fraction of new items are reported as already
+/// seen, and so are skipped. Items are never reported as new twice, except when the same item
+/// is inserted by two threads at once.
+pub struct BloomStore<T> {
+    bits: Vec<AtomicU64>,
+    num_bits: u64,
+    num_hashes: u32,
+    hashers: (RandomState, RandomState),
+    marker: PhantomData<fn(T)>,
+}
+
+impl<T: Hash> BloomStore<T> {
+    /// Create a store sized to hold `expected_items` with the given false positive rate,
+    /// e.g. `0.001` for one in a thousand.
+    pub fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        let expected_items = expected_items.max(1) as f64;
+        let false_positive_rate = false_positive_rate.clamp(f64::MIN_POSITIVE, 0.5);
+        let ln2 = std::f64::consts::LN_2;
+
+        // Optimal sizes from the standard Bloom filter analysis.
+        let num_bits = (-expected_items * false_positive_rate.ln() / (ln2 * ln2)).ceil() as u64;
+        let num_bits = num_bits.max(64);
+        let num_hashes = ((num_bits as f64 / expected_items) * ln2).round().max(1.0) as u32;
+
+        BloomStore {
+            bits: (0..num_bits.div_ceil(64))
+                .map(|_| AtomicU64::new(0))
+                .collect(),
+            num_bits,
+            num_hashes,
+            hashers: (RandomState::new(), RandomState::new()),
+            marker: PhantomData,
+        }
+    }
+
+    /// Determine whether the item may have been inserted, without inserting it.
+    pub fn contains(&self, item: &T) -> bool {
+        self.positions(item).all(|position| {
+            let mask = 1 << (position % 64);
+            self.bits[(position / 64) as usize].load(Ordering::Relaxed) & mask != 0
+        })
+    }
+
+    /// The number of bytes used by the filter.
+    pub fn memory_usage(&self) -> usize {
+        self.bits.len() * std::mem::size_of::<AtomicU64>()
+    }
+
+    /// The bit positions for an item, derived from two hashes by double hashing.
+    fn positions(&self, item: &T) -> impl Iterator<Item = u64> {
+        let h1 = self.hashers.0.hash_one(item);
+        let h2 = self.hashers.1.hash_one(item) | 1;
+        let num_bits = self.num_bits;
+        (0..self.num_hashes as u64).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+impl<T: Hash> VisitedStore<T> for BloomStore<T> {
+    fn insert_or_get(&self, item: T, _value: ()) -> Option<()> {
+        let mut new = false;
+        for position in self.positions(&item) {
+            let mask = 1 << (position % 64);
+            let previous = self.bits[(position / 64) as usize].fetch_or(mask, Ordering::Relaxed);
+            new |= previous & mask == 0;
+        }
+        if new {
+            None
+        } else {
+            Some(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BloomStore;
+    use crate::store::VisitedStore;
+
+    #[test]
+    fn insert() {
+        let store = BloomStore::new(100, 0.01);
+        assert!(store.insert("a"));
+        assert!(store.insert("b"));
+        assert!(!store.insert("a"));
+    }
+
+    #[test]
+    fn false_positive_rate() {
+        let expected_items = 10_000;
+        let store = BloomStore::new(expected_items, 0.01);
+        for i in 0..expected_items {
+            store.insert(i);
+        }
+
+        // Every inserted item is reported as seen, and few new items are.
+        assert!((0..expected_items).all(|i| store.contains(&i)));
+        let false_positives = (expected_items..2 * expected_items)
+            .filter(|i| store.contains(i))
+            .count();
+        assert!(false_positives < expected_items / 50);
+
+        // Roughly 9.6 bits per item for a 1% false positive rate.
+        assert!(store.memory_usage() < expected_items * 10 / 8 + 8);
+    }
+}
@@ -0,0 +1,126 @@
+use crate::{
+    results::{CrawlResults, PageResult},
+    types::ScheduledUrl,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufReader, BufWriter},
+    path::Path,
+};
+use url::Url;
+
+const FILE_NAME: &str = "checkpoint.json";
+
+/// A snapshot of a crawl in progress, which can be saved to a directory and resumed from.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub seed: Url,
+    /// URLs which were scheduled but not yet visited.
+    pub frontier: Vec<ScheduledUrl>,
+    /// URLs which were visited or scheduled, and so should not be scheduled again.
+    pub visited: Vec<Url>,
+    /// The content hash of each page mapped to the URL it was first seen at.
+    pub hashes: BTreeMap<String, Url>,
+    /// The pages visited so far.
+    pub pages: Vec<PageResult>,
+    /// The SimHash fingerprint of each page which wasn't a near duplicate, with its URL, when
+    /// detecting near duplicates.
+    #[serde(default)]
+    pub fingerprints: Vec<(u64, Url)>,
+}
+
+impl Checkpoint {
+    pub fn new(seed: Url, frontier: Vec<ScheduledUrl>, results: &CrawlResults) -> Self {
+        let pages = results.pages();
+        let mut visited: Vec<Url> = pages.iter().map(|page| page.url.clone()).collect();
+        visited.extend(frontier.iter().map(|scheduled| scheduled.url.clone()));
+
+        // Exact duplicates share the hash of the page they duplicate, so record the pages
+        // which aren't duplicates first. Near duplicates keep a hash of their own.
+        let mut hashes = BTreeMap::new();
+        let (originals, duplicates): (Vec<_>, Vec<_>) =
+            pages.iter().partition(|page| page.duplicate_of.is_none());
+        for page in originals.into_iter().chain(duplicates) {
+            if let Some(hash) = &page.content_hash {
+                hashes
+                    .entry(hash.clone())
+                    .or_insert_with(|| page.url.clone());
+            }
+        }
+
+        Checkpoint {
+            seed,
+            frontier,
+            visited,
+            hashes,
+            pages,
+            fingerprints: Vec::new(),
+        }
+    }
+
+    /// Record the fingerprints of a `NearDuplicateDetector`, so that it can be restored.
+    pub fn with_fingerprints(mut self, fingerprints: Vec<(u64, Url)>) -> Self {
+        self.fingerprints = fingerprints;
+        self
+    }
+
+    /// Save the checkpoint to the directory, replacing any earlier checkpoint. The file is
+    /// written alongside and then renamed, so an interrupted save leaves the earlier one intact.
+    pub fn save(&self, dir: &Path) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(FILE_NAME);
+        let partial = dir.join(format!("{}.partial", FILE_NAME));
+        serde_json::to_writer(BufWriter::new(fs::File::create(&partial)?), self)?;
+        fs::rename(partial, path)
+    }
+
+    /// Load the checkpoint saved in the directory.
+    pub fn load(dir: &Path) -> io::Result<Self> {
+        let file = fs::File::open(dir.join(FILE_NAME))?;
+        Ok(serde_json::from_reader(BufReader::new(file))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Checkpoint;
+    use crate::{
+        results::{CrawlResults, PageResult},
+        types::ScheduledUrl,
+    };
+    use reqwest::StatusCode;
+    use url::Url;
+
+    #[test]
+    fn save_and_load() {
+        let seed = Url::parse("https://example.com/").unwrap();
+        let copy = seed.join("copy").unwrap();
+        let pending = ScheduledUrl::seed(seed.clone()).link(seed.join("pending").unwrap());
+
+        let results = CrawlResults::new();
+        let mut page = PageResult::new(seed.clone(), seed.clone(), StatusCode::OK);
+        page.content_hash = Some("abc".to_string());
+        results.insert(page);
+        let mut page = PageResult::new(copy.clone(), copy.clone(), StatusCode::OK);
+        page.content_hash = Some("abc".to_string());
+        page.duplicate_of = Some(seed.clone());
+        results.insert(page);
+
+        let dir = tempfile::tempdir().unwrap();
+        Checkpoint::new(seed.clone(), vec![pending.clone()], &results)
+            .with_fingerprints(vec![(42, seed.clone())])
+            .save(dir.path())
+            .unwrap();
+        let checkpoint = Checkpoint::load(dir.path()).unwrap();
+
+        assert_eq!(checkpoint.seed, seed);
+        assert_eq!(checkpoint.frontier, vec![pending.clone()]);
+        assert_eq!(checkpoint.visited.len(), 3);
+        assert!(checkpoint.visited.contains(&pending.url));
+        assert_eq!(checkpoint.hashes.get("abc"), Some(&seed));
+        assert_eq!(checkpoint.pages.len(), 2);
+        assert_eq!(checkpoint.fingerprints, vec![(42, seed)]);
+    }
+}
@@ -0,0 +1,335 @@
+use crate::shutdown::Shutdown;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug, Formatter},
+    path::PathBuf,
+    time::Duration,
+};
+use url::Url;
+
+/// Where the visited URLs and page hashes are kept during a crawl.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum StoreBackend {
+    /// Keep everything in memory, in a store sharded across several locks.
+    #[default]
+    Memory,
+    /// Keep everything in an embedded database in the given directory.
+    Disk(PathBuf),
+    /// Keep visited URLs in a Bloom filter sized for the expected number of URLs, which skips
+    /// roughly `false_positive_rate` of new URLs. Page hashes are kept in memory.
+    Bloom {
+        expected_items: usize,
+        false_positive_rate: f64,
+    },
+}
+
+/// The user agent sent when none is configured.
+pub const DEFAULT_USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64)";
+
+/// How requests are made.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientConfig {
+    /// The `User-Agent` header. Its first word is also the agent matched in robots.txt.
+    pub user_agent: String,
+    /// Give up on a request which hasn't completed within this time.
+    pub timeout: Option<Duration>,
+    /// Give up on a request which hasn't connected within this time.
+    pub connect_timeout: Option<Duration>,
+    /// The most requests per second made to each host. Unlimited when unset.
+    pub rate_limit: Option<f64>,
+    /// Headers sent with every request to a host in the crawl's scope. Headers configured for
+    /// a host take precedence.
+    pub headers: BTreeMap<String, String>,
+    /// Send requests through this proxy, given as an `http`, `https`, `socks5` or `socks5h`
+    /// URL. When unset, the `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and `NO_PROXY` environment
+    /// variables are used.
+    pub proxy: Option<Url>,
+    /// Hosts reached directly instead of through the proxy, whether it is set above or in the
+    /// environment. A domain also matches its subdomains, and IP ranges can be given as e.g.
+    /// `10.0.0.0/8`.
+    pub no_proxy: Vec<String>,
+    /// A PEM file of CA certificates to trust in addition to the system's, e.g. for a proxy
+    /// which intercepts TLS.
+    pub ca_bundle: Option<PathBuf>,
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            timeout: None,
+            connect_timeout: None,
+            rate_limit: None,
+            headers: BTreeMap::new(),
+            proxy: None,
+            no_proxy: Vec::new(),
+            ca_bundle: None,
+        }
+    }
+}
+
+/// Settings which apply to requests to a single host, overriding those of the crawl. Hosts are
+/// keyed by name, or by name and port to only match that port.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HostConfig {
+    /// The most requests per second made to the host.
+    pub rate_limit: Option<f64>,
+    /// Headers sent with every request to the host.
+    pub headers: BTreeMap<String, String>,
+    /// Credentials sent with every request to the host, and never to any other.
+    pub credentials: Option<Credentials>,
+    /// Only follow links to the host whose path starts with one of these prefixes. Every path
+    /// is followed when empty.
+    pub include: Vec<String>,
+    /// Never follow links to the host whose path starts with one of these prefixes.
+    pub exclude: Vec<String>,
+}
+
+impl HostConfig {
+    /// Whether links to the path are followed, according to `include` and `exclude`.
+    pub fn allows_path(&self, path: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|prefix| path.starts_with(prefix)))
+            && !self.exclude.iter().any(|prefix| path.starts_with(prefix))
+    }
+}
+
+/// The entry for the URL's host in a map keyed by host name. An entry for the URL's origin,
+/// e.g. `http://localhost:8080`, takes precedence over one for the host and port, e.g.
+/// `localhost:8080`, which takes precedence over one for the host on any port.
+pub fn host_entry<'a, T>(entries: &'a BTreeMap<String, T>, url: &Url) -> Option<&'a T> {
+    let host = url.host_str()?;
+    entries
+        .get(&url.origin().ascii_serialization())
+        .or_else(|| {
+            url.port_or_known_default()
+                .and_then(|port| entries.get(&format!("{}:{}", host, port)))
+        })
+        .or_else(|| entries.get(host))
+}
+
+/// Whether credentials for the URL's host may be sent to it. They're only sent over `https`,
+/// unless the host is configured by its `http` origin, e.g. `http://localhost:8080`.
+pub fn sends_credentials<T>(entries: &BTreeMap<String, T>, url: &Url) -> bool {
+    url.scheme() == "https" || entries.contains_key(&url.origin().ascii_serialization())
+}
+
+/// Credentials for HTTP authentication.
+#[derive(Clone, PartialEq)]
+pub enum Credentials {
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    Bearer(String),
+}
+
+/// Leave secrets out of debug output.
+impl Debug for Credentials {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Credentials::Basic { username, .. } => f
+                .debug_struct("Basic")
+                .field("username", username)
+                .finish_non_exhaustive(),
+            Credentials::Bearer(_) => f.write_str("Bearer(..)"),
+        }
+    }
+}
+
+/// A form submitted before the crawl starts, so that pages which need a session can be visited.
+/// The session cookies set in response are sent with every request of the crawl.
+#[derive(Clone, PartialEq)]
+pub struct LoginConfig {
+    /// The page with the login form.
+    pub url: Url,
+    /// A CSS selector for the form. When unset, the first form with a password field.
+    pub form: Option<String>,
+    /// Values for named fields, such as the username and password. They replace the values
+    /// in the form, while other fields such as hidden CSRF tokens are submitted as they are.
+    pub fields: BTreeMap<String, String>,
+}
+
+/// Leave field values, such as passwords, out of debug output.
+impl Debug for LoginConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LoginConfig")
+            .field("url", &self.url)
+            .field("form", &self.form)
+            .field("fields", &self.fields.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+/// Which links are followed, relative to the seed URL.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// Links to the same host as the seed URL.
+    #[default]
+    Host,
+    /// Links to the seed URL's domain or any of its subdomains, ignoring a leading `www.`.
+    Domain,
+    /// Links to the same host below the seed URL's directory. For example, with a seed URL of
+    /// `/docs/index.html`, only links starting with `/docs/` are followed.
+    Prefix,
+}
+
+impl Scope {
+    /// Whether links to the host are in the scope of a crawl from the seed URL's host, whatever
+    /// their path.
+    pub fn includes_host(&self, subdomain: &str, host: &str) -> bool {
+        match self {
+            Scope::Host | Scope::Prefix => host == subdomain,
+            Scope::Domain => {
+                let domain = subdomain.trim_start_matches("www.");
+                host == domain || host.ends_with(&format!(".{}", domain))
+            }
+        }
+    }
+}
+
+/// How each visited page is written by the default output sink.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum OutputFormat {
+    /// Each URL followed by its links, for reading.
+    #[default]
+    Text,
+    /// One JSON object per line, for other tools. See the README for the schema.
+    JsonLines,
+    /// One row of comma separated values per page, after a header row.
+    Csv,
+    /// The visited pages as a hierarchy, printed once the crawl completes.
+    Tree(TreeGrouping),
+    /// A self-contained HTML report, written once the crawl completes.
+    Html,
+}
+
+/// Parse the names given to `--format`.
+impl TryFrom<String> for OutputFormat {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Self, Self::Error> {
+        match name.as_str() {
+            "text" => Ok(OutputFormat::Text),
+            "jsonl" => Ok(OutputFormat::JsonLines),
+            "csv" => Ok(OutputFormat::Csv),
+            "tree" => Ok(OutputFormat::Tree(TreeGrouping::Path)),
+            "discovery-tree" => Ok(OutputFormat::Tree(TreeGrouping::Discovery)),
+            "html" => Ok(OutputFormat::Html),
+            _ => Err(format!(
+                "unknown format `{}`, expected one of text, jsonl, csv, tree, discovery-tree, html",
+                name
+            )),
+        }
+    }
+}
+
+/// How pages are arranged in the tree output.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TreeGrouping {
+    /// By path segment.
+    #[default]
+    Path,
+    /// By the page each was first found on.
+    Discovery,
+}
+
+/// The order pages are written in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputOrder {
+    /// As soon as each page is visited, which varies between crawls.
+    #[default]
+    Visited,
+    /// Breadth first from the seed URL, following links in URL order. Pages are held back
+    /// until the crawl completes.
+    Discovery,
+    /// Sorted by URL. Pages are held back until the crawl completes.
+    Url,
+}
+
+/// Where and how often the progress of a crawl is saved, so that it can be resumed.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CheckpointConfig {
+    pub dir: PathBuf,
+    pub interval: Duration,
+}
+
+impl CheckpointConfig {
+    /// Save a checkpoint to the directory every 30 seconds.
+    pub fn new(dir: PathBuf) -> Self {
+        CheckpointConfig {
+            dir,
+            interval: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Settings which control how a crawl behaves.
+#[derive(Clone, Debug, Default)]
+pub struct Config {
+    /// How requests are made.
+    pub client: ClientConfig,
+    /// The most pages fetched at once. Unlimited when unset.
+    pub concurrency: Option<usize>,
+    /// The most links followed from the seed URL. Links on pages at this depth are reported
+    /// but not followed. Unlimited when unset.
+    pub max_depth: Option<usize>,
+    /// Which links are followed, relative to the seed URL.
+    pub scope: Scope,
+    /// Follow links and report pages regardless of `rel="nofollow"`, `<meta name="robots">`
+    /// and `X-Robots-Tag` directives. Useful for audit crawls which need to see every page.
+    pub ignore_robots_directives: bool,
+    /// When a page declares a different canonical URL, schedule the canonical URL instead of
+    /// following the links of the duplicate. Duplicates known from `previous_results` aren't
+    /// fetched again.
+    pub dedupe_by_canonical: bool,
+    /// Once the crawl completes, check canonical and hreflang links for consistency.
+    pub audit: bool,
+    /// Once the crawl completes, request the target of each link to another host, without
+    /// following its links, so that broken external links are reported by
+    /// `CrawlResults::broken_links`.
+    pub check_external_links: bool,
+    /// Treat pages whose visible text is at least this similar to an earlier page as
+    /// duplicates, between 0.0 and 1.0. Only identical bodies are detected when unset.
+    pub near_duplicate_threshold: Option<f64>,
+    /// Where the visited URLs and page hashes are kept.
+    pub store: StoreBackend,
+    /// Periodically save the progress of the crawl.
+    pub checkpoint: Option<CheckpointConfig>,
+    /// Stops the crawl early when triggered, e.g. by Ctrl-C.
+    pub shutdown: Shutdown,
+    /// Results saved by a previous crawl. Pages are requested with the `ETag` and
+    /// `Last-Modified` values from then, and reuse the links found then if unmodified.
+    pub previous_results: Option<PathBuf>,
+    /// Save the results to this file once the crawl completes.
+    pub save_results: Option<PathBuf>,
+    /// A Netscape `cookies.txt` file whose cookies are sent with requests. It is replaced by
+    /// every cookie held once the crawl completes, including those set by the server.
+    pub cookies: Option<PathBuf>,
+    /// How each visited page is written to stdout.
+    pub format: OutputFormat,
+    /// The order pages are written in.
+    pub order: OutputOrder,
+    /// Once the crawl completes, write every link between pages to this file, as tab
+    /// separated values for a `.tsv` file and comma separated values otherwise.
+    pub export_edges: Option<PathBuf>,
+    /// Once the crawl completes, write the status and depth of every visited page to this file,
+    /// in the same format as the edges.
+    pub export_nodes: Option<PathBuf>,
+    /// Once the crawl completes, write the link graph to this file, as GraphML for a
+    /// `.graphml` file and Graphviz DOT otherwise.
+    pub export_graph: Option<PathBuf>,
+    /// Collapse the pages in the exported graph into a node per path prefix of this many
+    /// segments.
+    pub collapse_depth: Option<usize>,
+    /// Settings for requests to particular hosts, keyed by host name.
+    pub hosts: BTreeMap<String, HostConfig>,
+    /// A `.netrc` file with the credentials for hosts which don't have their own in `hosts`.
+    pub netrc: Option<PathBuf>,
+    /// Log in before the crawl starts. Links which look like they log out are then not
+    /// followed.
+    pub login: Option<LoginConfig>,
+}
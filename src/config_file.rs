@@ -0,0 +1,428 @@
+use crate::{
+    config::{
+        CheckpointConfig, ClientConfig, Config, Credentials, HostConfig, LoginConfig, OutputFormat,
+        OutputOrder, Scope,
+    },
+    types::CrawlerError,
+};
+use reqwest::header::{HeaderName, HeaderValue};
+use scraper::Selector;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use url::Url;
+
+/// The kinds of proxy requests can be sent through.
+const PROXY_SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+/// The settings of a crawl read from a TOML file, with a key for every command line option
+/// and a table of overrides per host. Every key is optional, and unknown keys are errors.
+///
+/// ```toml
+/// seed = "https://example.com/"
+/// depth = 3
+/// rate-limit = 5
+///
+/// [hosts."docs.example.com"]
+/// rate-limit = 1
+/// headers = { X-Preview-Key = "abc123" }
+/// auth = { type = "bearer", token-env = "DOCS_TOKEN" }
+/// exclude = ["/archive/"]
+/// ```
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ConfigFile {
+    pub seed: Option<Url>,
+    pub concurrency: Option<usize>,
+    pub depth: Option<usize>,
+    pub scope: Option<Scope>,
+    pub output: Option<PathBuf>,
+    pub user_agent: Option<String>,
+    /// In seconds.
+    pub timeout: Option<f64>,
+    /// In seconds.
+    pub connect_timeout: Option<f64>,
+    /// The most requests per second made to each host.
+    pub rate_limit: Option<f64>,
+    /// Headers sent with every request.
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// The proxy requests are sent through.
+    pub proxy: Option<Url>,
+    /// Hosts reached without the proxy.
+    #[serde(default)]
+    pub no_proxy: Vec<String>,
+    /// A PEM file of extra CA certificates to trust.
+    pub ca_bundle: Option<PathBuf>,
+    /// A Netscape `cookies.txt` file.
+    pub cookies: Option<PathBuf>,
+    /// A `.netrc` file with credentials for hosts.
+    pub netrc: Option<PathBuf>,
+    /// A form to log in with before crawling.
+    pub login: Option<LoginSection>,
+    pub format: Option<OutputFormat>,
+    pub order: Option<OutputOrder>,
+    pub checkpoint: Option<PathBuf>,
+    /// A checkpoint directory to continue the crawl from, in place of the seed.
+    pub resume: Option<PathBuf>,
+    pub save: Option<PathBuf>,
+    pub previous: Option<PathBuf>,
+    pub edges: Option<PathBuf>,
+    pub nodes: Option<PathBuf>,
+    pub graph: Option<PathBuf>,
+    pub collapse: Option<usize>,
+    /// Overrides for particular hosts, keyed by host name.
+    #[serde(default)]
+    pub hosts: BTreeMap<String, HostSection>,
+    /// The file the settings were read from, for error messages.
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+/// The settings for requests to a single host.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct HostSection {
+    pub rate_limit: Option<f64>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub auth: Option<AuthSection>,
+    /// Only follow links to paths starting with one of these prefixes.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Never follow links to paths starting with one of these prefixes.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+}
+
+/// The login form and the values to fill it with.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct LoginSection {
+    pub url: Url,
+    /// A CSS selector for the form.
+    pub form: Option<String>,
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// The environment variable holding the value of each field, such as the password.
+    #[serde(default)]
+    pub fields_env: BTreeMap<String, String>,
+}
+
+/// How to authenticate with a host. Secrets are read from environment variables so that they
+/// can be kept out of the file.
+#[derive(Debug, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "kebab-case",
+    rename_all_fields = "kebab-case",
+    deny_unknown_fields
+)]
+pub enum AuthSection {
+    Basic {
+        username: String,
+        password_env: Option<String>,
+    },
+    Bearer {
+        token_env: String,
+    },
+}
+
+impl ConfigFile {
+    /// Read the settings from a file.
+    pub fn load(path: &Path) -> Result<Self, CrawlerError> {
+        let error = |message: String| CrawlerError::ConfigError {
+            path: path.to_path_buf(),
+            message,
+        };
+        let text = fs::read_to_string(path).map_err(|e| error(e.to_string()))?;
+        let mut file: ConfigFile = toml::from_str(&text).map_err(|e| error(e.to_string()))?;
+        file.path = path.to_path_buf();
+        Ok(file)
+    }
+
+    /// The crawl configuration described by the file.
+    pub fn config(&self) -> Result<Config, CrawlerError> {
+        let client = ClientConfig {
+            user_agent: self
+                .user_agent
+                .clone()
+                .unwrap_or_else(|| ClientConfig::default().user_agent),
+            timeout: self.seconds("timeout", self.timeout)?,
+            connect_timeout: self.seconds("connect-timeout", self.connect_timeout)?,
+            rate_limit: self.rate("rate-limit", self.rate_limit)?,
+            headers: self.headers("headers", &self.headers)?,
+            proxy: self.proxy()?,
+            no_proxy: self.no_proxy.clone(),
+            ca_bundle: self.ca_bundle.clone(),
+        };
+        let hosts = self
+            .hosts
+            .iter()
+            .map(|(host, section)| Ok((host.clone(), self.host_config(host, section)?)))
+            .collect::<Result<_, CrawlerError>>()?;
+        Ok(Config {
+            client,
+            concurrency: self.concurrency()?,
+            max_depth: self.depth,
+            scope: self.scope.unwrap_or_default(),
+            format: self.format.unwrap_or_default(),
+            order: self.order.unwrap_or_default(),
+            checkpoint: self.checkpoint.clone().map(CheckpointConfig::new),
+            save_results: self.save.clone(),
+            cookies: self.cookies.clone(),
+            netrc: self.netrc.clone(),
+            login: self
+                .login
+                .as_ref()
+                .map(|login| self.login(login))
+                .transpose()?,
+            previous_results: self.previous.clone(),
+            export_edges: self.edges.clone(),
+            export_nodes: self.nodes.clone(),
+            export_graph: self.graph.clone(),
+            collapse_depth: self.collapse,
+            hosts,
+            ..Default::default()
+        })
+    }
+
+    fn host_config(&self, host: &str, section: &HostSection) -> Result<HostConfig, CrawlerError> {
+        let key = |name: &str| format!("hosts.\"{}\".{}", host, name);
+        let credentials = match &section.auth {
+            Some(AuthSection::Basic {
+                username,
+                password_env,
+            }) => Some(Credentials::Basic {
+                username: username.clone(),
+                password: match password_env {
+                    Some(name) => Some(self.env(name)?),
+                    None => None,
+                },
+            }),
+            Some(AuthSection::Bearer { token_env }) => {
+                Some(Credentials::Bearer(self.env(token_env)?))
+            }
+            None => None,
+        };
+        Ok(HostConfig {
+            rate_limit: self.rate(&key("rate-limit"), section.rate_limit)?,
+            headers: self.headers(&key("headers"), &section.headers)?,
+            credentials,
+            include: section.include.clone(),
+            exclude: section.exclude.clone(),
+        })
+    }
+
+    fn login(&self, section: &LoginSection) -> Result<LoginConfig, CrawlerError> {
+        if let Some(form) = &section.form {
+            if Selector::parse(form).is_err() {
+                return Err(self.error(format!("login.form {} isn't a CSS selector", form)));
+            }
+        }
+        let mut fields = section.fields.clone();
+        for (field, name) in &section.fields_env {
+            fields.insert(field.clone(), self.env(name)?);
+        }
+        Ok(LoginConfig {
+            url: section.url.clone(),
+            form: section.form.clone(),
+            fields,
+        })
+    }
+
+    fn proxy(&self) -> Result<Option<Url>, CrawlerError> {
+        match &self.proxy {
+            Some(proxy) if !PROXY_SCHEMES.contains(&proxy.scheme()) => Err(self.error(format!(
+                "proxy {} must be an http, https, socks5 or socks5h URL",
+                proxy
+            ))),
+            _ => Ok(self.proxy.clone()),
+        }
+    }
+
+    fn headers(
+        &self,
+        key: &str,
+        headers: &BTreeMap<String, String>,
+    ) -> Result<BTreeMap<String, String>, CrawlerError> {
+        for (name, value) in headers {
+            if HeaderName::try_from(name).is_err() || HeaderValue::try_from(value).is_err() {
+                return Err(self.error(format!("{}.{} is an invalid header", key, name)));
+            }
+        }
+        Ok(headers.clone())
+    }
+
+    fn seconds(&self, key: &str, value: Option<f64>) -> Result<Option<Duration>, CrawlerError> {
+        value
+            .map(|seconds| {
+                Duration::try_from_secs_f64(seconds)
+                    .map_err(|_| self.error(format!("{} isn't a number of seconds", key)))
+            })
+            .transpose()
+    }
+
+    fn concurrency(&self) -> Result<Option<usize>, CrawlerError> {
+        match self.concurrency {
+            Some(0) => Err(self.error("concurrency must be more than 0".to_string())),
+            concurrency => Ok(concurrency),
+        }
+    }
+
+    fn rate(&self, key: &str, value: Option<f64>) -> Result<Option<f64>, CrawlerError> {
+        match value {
+            Some(rate) if !(rate.is_finite() && rate > 0.0) => {
+                Err(self.error(format!("{} must be more than 0", key)))
+            }
+            _ => Ok(value),
+        }
+    }
+
+    fn env(&self, name: &str) -> Result<String, CrawlerError> {
+        env::var(name)
+            .map_err(|_| self.error(format!("the environment variable {} isn't set", name)))
+    }
+
+    fn error(&self, message: String) -> CrawlerError {
+        CrawlerError::ConfigError {
+            path: self.path.clone(),
+            message,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ConfigFile;
+    use crate::{
+        config::{Credentials, OutputFormat, Scope, TreeGrouping},
+        types::CrawlerError,
+    };
+    use std::{fs, time::Duration};
+    use tempfile::TempDir;
+
+    fn load(text: &str) -> Result<ConfigFile, CrawlerError> {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("crawler.toml");
+        fs::write(&path, text).unwrap();
+        ConfigFile::load(&path)
+    }
+
+    #[test]
+    fn load_settings() {
+        std::env::set_var("CONFIG_FILE_TEST_TOKEN", "secret");
+        let file = load(
+            r#"
+            seed = "https://example.com/"
+            depth = 2
+            scope = "domain"
+            timeout = 10
+            rate-limit = 2.5
+            format = "discovery-tree"
+            headers = { Authorization = "Bearer abc" }
+            cookies = "cookies.txt"
+            resume = "checkpoint"
+            proxy = "socks5h://proxy.internal:1080"
+            no-proxy = ["intranet.example.com", "10.0.0.0/8"]
+            ca-bundle = "corporate-ca.pem"
+
+            [login]
+            url = "https://example.com/login"
+            fields = { username = "ci" }
+            fields-env = { password = "CONFIG_FILE_TEST_TOKEN" }
+
+            [hosts."docs.example.com"]
+            rate-limit = 1
+            headers = { X-Preview-Key = "abc" }
+            auth = { type = "bearer", token-env = "CONFIG_FILE_TEST_TOKEN" }
+            exclude = ["/archive/"]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(file.seed.as_ref().unwrap().as_str(), "https://example.com/");
+        assert_eq!(file.resume, Some("checkpoint".into()));
+
+        let config = file.config().unwrap();
+        assert_eq!(config.max_depth, Some(2));
+        assert_eq!(config.scope, Scope::Domain);
+        assert_eq!(config.client.timeout, Some(Duration::from_secs(10)));
+        assert_eq!(config.client.rate_limit, Some(2.5));
+        assert_eq!(config.format, OutputFormat::Tree(TreeGrouping::Discovery));
+        assert_eq!(config.client.headers["Authorization"], "Bearer abc");
+        assert_eq!(config.cookies, Some("cookies.txt".into()));
+        assert_eq!(
+            config.client.proxy.unwrap().as_str(),
+            "socks5h://proxy.internal:1080"
+        );
+        assert_eq!(config.client.no_proxy.len(), 2);
+        assert_eq!(config.client.ca_bundle, Some("corporate-ca.pem".into()));
+        let host = &config.hosts["docs.example.com"];
+        assert_eq!(host.rate_limit, Some(1.0));
+        assert_eq!(host.headers["X-Preview-Key"], "abc");
+        assert_eq!(
+            host.credentials,
+            Some(Credentials::Bearer("secret".to_string()))
+        );
+        assert!(!host.allows_path("/archive/2020"));
+        let login = config.login.unwrap();
+        assert_eq!(login.fields["username"], "ci");
+        assert_eq!(login.fields["password"], "secret");
+    }
+
+    #[test]
+    fn reject_unknown_keys() {
+        let error = load("depth = 2\nmax-pages = 10\n").unwrap_err();
+        let CrawlerError::ConfigError { message, .. } = error else {
+            panic!("unexpected error {:?}", error);
+        };
+        assert!(message.contains("unknown field `max-pages`"), "{}", message);
+
+        let error = load("[hosts.\"example.com\"]\nheader = { A = \"b\" }\n").unwrap_err();
+        assert!(error.to_string().contains("unknown field `header`"));
+    }
+
+    #[test]
+    fn reject_invalid_values() {
+        let error = load("format = \"xml\"\n").unwrap_err();
+        assert!(error.to_string().contains("unknown format `xml`"));
+
+        let file = load("headers = { \"X Key\" = \"a\" }\n").unwrap();
+        let error = file.config().unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("headers.X Key is an invalid header"));
+
+        let file = load("proxy = \"ftp://proxy.internal/\"\n").unwrap();
+        let error = file.config().unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("must be an http, https, socks5 or socks5h URL"));
+
+        let file = load("concurrency = 0\n").unwrap();
+        let error = file.config().unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("concurrency must be more than 0"));
+
+        let file = load("[hosts.\"example.com\"]\nrate-limit = 0\n").unwrap();
+        let error = file.config().unwrap_err();
+        assert!(error
+            .to_string()
+            .contains(r#"hosts."example.com".rate-limit must be more than 0"#));
+
+        let file = load(
+            "[hosts.\"example.com\"]\nauth = { type = \"bearer\", token-env = \"CONFIG_FILE_TEST_UNSET\" }\n",
+        )
+        .unwrap();
+        assert!(file
+            .config()
+            .unwrap_err()
+            .to_string()
+            .contains("CONFIG_FILE_TEST_UNSET isn't set"));
+    }
+}
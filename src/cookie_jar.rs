@@ -0,0 +1,194 @@
+use cookie::{time::OffsetDateTime, Cookie};
+use cookie_store::{CookieDomain, CookieExpiration, CookieStore};
+use reqwest_cookie_store::CookieStoreMutex;
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, MutexGuard},
+};
+use url::Url;
+
+/// The cookies sent with every request of a crawl. Cookies set by the server are kept for the
+/// rest of the crawl, and can be read from and saved to a Netscape `cookies.txt` file, as
+/// exported by browsers and written by `curl -c`.
+#[derive(Clone, Default)]
+pub struct CookieJar {
+    store: Arc<CookieStoreMutex>,
+}
+
+/// Marks an `HttpOnly` cookie in a `cookies.txt` file, in place of a comment.
+const HTTP_ONLY_PREFIX: &str = "#HttpOnly_";
+
+impl CookieJar {
+    /// The store for the HTTP client to read and write cookies through.
+    pub fn provider(&self) -> Arc<CookieStoreMutex> {
+        self.store.clone()
+    }
+
+    /// Add the cookies in a `cookies.txt` file.
+    pub fn load(&self, path: &Path) -> io::Result<()> {
+        self.read(BufReader::new(fs::File::open(path)?))
+    }
+
+    /// Add the cookies in the `cookies.txt` format, one per line as `domain`,
+    /// `include subdomains`, `path`, `secure`, `expires` (a Unix timestamp, or 0 for the end
+    /// of the session), `name` and `value`, separated by tabs. Malformed lines are skipped.
+    pub fn read(&self, reader: impl BufRead) -> io::Result<()> {
+        let mut store = self.lock()?;
+        for line in reader.lines() {
+            let line = line?;
+            let (line, http_only) = match line.strip_prefix(HTTP_ONLY_PREFIX) {
+                Some(line) => (line, true),
+                None => (line.as_str(), false),
+            };
+            if line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<_> = line.trim_end_matches('\r').split('\t').collect();
+            let [domain, subdomains, path, secure, expires, name, value] = fields[..] else {
+                continue;
+            };
+            let host = domain.trim_start_matches('.');
+            let secure = secure.eq_ignore_ascii_case("TRUE");
+            let scheme = if secure { "https" } else { "http" };
+            let Ok(url) = Url::parse(&format!("{}://{}{}", scheme, host, path)) else {
+                continue;
+            };
+
+            let mut cookie = Cookie::build((name.to_string(), value.to_string()))
+                .path(path.to_string())
+                .secure(secure)
+                .http_only(http_only);
+            if subdomains.eq_ignore_ascii_case("TRUE") {
+                cookie = cookie.domain(host.to_string());
+            }
+            match expires.parse::<i64>() {
+                Ok(0) => {}
+                Ok(timestamp) => match OffsetDateTime::from_unix_timestamp(timestamp) {
+                    Ok(expires) => cookie = cookie.expires(expires),
+                    Err(_) => continue,
+                },
+                Err(_) => continue,
+            }
+            // Cookies which have already expired are left out.
+            let _ = store.insert_raw(&cookie.build(), &url);
+        }
+        Ok(())
+    }
+
+    /// Save every unexpired cookie to a `cookies.txt` file, replacing its contents.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut wrt = BufWriter::new(fs::File::create(path)?);
+        self.write(&mut wrt)?;
+        wrt.flush()
+    }
+
+    /// Write every unexpired cookie in the `cookies.txt` format.
+    pub fn write(&self, mut wrt: impl Write) -> io::Result<()> {
+        let store = self.lock()?;
+        writeln!(wrt, "# Netscape HTTP Cookie File")?;
+        for cookie in store.iter_unexpired() {
+            let (domain, subdomains) = match &cookie.domain {
+                CookieDomain::HostOnly(domain) => (domain.clone(), false),
+                CookieDomain::Suffix(domain) => (format!(".{}", domain), true),
+                CookieDomain::NotPresent | CookieDomain::Empty => continue,
+            };
+            let expires = match &cookie.expires {
+                CookieExpiration::AtUtc(expires) => expires.unix_timestamp(),
+                CookieExpiration::SessionEnd => 0,
+            };
+            let flag = |value: bool| if value { "TRUE" } else { "FALSE" };
+            writeln!(
+                wrt,
+                "{}{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                if cookie.http_only() == Some(true) {
+                    HTTP_ONLY_PREFIX
+                } else {
+                    ""
+                },
+                domain,
+                flag(subdomains),
+                String::from(&cookie.path),
+                flag(cookie.secure() == Some(true)),
+                expires,
+                cookie.name(),
+                cookie.value()
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Lock the store, reporting a poisoned lock as an error.
+    fn lock(&self) -> io::Result<MutexGuard<'_, CookieStore>> {
+        self.store.lock().map_err(|_| {
+            eprintln!("CookieJar lock poisoned");
+            io::Error::other("CookieJar lock poisoned")
+        })
+    }
+
+    /// The name and value of each cookie which would be sent with a request to the URL.
+    pub fn cookies(&self, url: &Url) -> Vec<(String, String)> {
+        let Ok(store) = self.lock() else {
+            return Vec::new();
+        };
+        store
+            .get_request_values(url)
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CookieJar;
+    use url::Url;
+
+    const COOKIES: &str = "# Netscape HTTP Cookie File\n\
+        example.com\tFALSE\t/\tFALSE\t0\tsession\tabc\n\
+        .example.com\tTRUE\t/docs\tTRUE\t4102444800\tpreview\tyes\n\
+        #HttpOnly_example.com\tFALSE\t/\tFALSE\t0\ttoken\tsecret\n\
+        example.com\tFALSE\t/\tFALSE\t1\texpired\told\n\
+        not a cookie\n";
+
+    #[test]
+    fn read_cookies() {
+        let jar = CookieJar::default();
+        jar.read(COOKIES.as_bytes()).unwrap();
+
+        let mut cookies = jar.cookies(&Url::parse("https://docs.example.com/docs/a").unwrap());
+        cookies.sort();
+        assert_eq!(cookies, vec![("preview".to_string(), "yes".to_string())]);
+
+        let mut cookies = jar.cookies(&Url::parse("http://example.com/").unwrap());
+        cookies.sort();
+        assert_eq!(
+            cookies,
+            vec![
+                ("session".to_string(), "abc".to_string()),
+                ("token".to_string(), "secret".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn write_cookies() {
+        let jar = CookieJar::default();
+        jar.read(COOKIES.as_bytes()).unwrap();
+        let mut output = Vec::new();
+        jar.write(&mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        let mut lines: Vec<_> = output.lines().collect();
+        assert_eq!(lines.remove(0), "# Netscape HTTP Cookie File");
+        lines.sort();
+        assert_eq!(
+            lines,
+            vec![
+                "#HttpOnly_example.com\tFALSE\t/\tFALSE\t0\ttoken\tsecret",
+                ".example.com\tTRUE\t/docs\tTRUE\t4102444800\tpreview\tyes",
+                "example.com\tFALSE\t/\tFALSE\t0\tsession\tabc",
+            ]
+        );
+    }
+}
@@ -1,84 +1,692 @@
 use crate::{
-    html_downloader::HtmlDownloader, html_parser::HtmlParser, printer::Printer,
-    types::CrawlerError, url_filter::UrlFilter,
+    audit::Auditor,
+    bloom_store::BloomStore,
+    checkpoint::Checkpoint,
+    config::{CheckpointConfig, Config, StoreBackend},
+    disk_store::DiskStore,
+    duplicate::{ExactDuplicateDetector, NearDuplicateDetector},
+    edge_list::EdgeList,
+    html_downloader::HtmlDownloader,
+    html_parser::HtmlParser,
+    login::Login,
+    netrc::Netrc,
+    output::{OutputSink, SortedSink},
+    results::{CrawlResults, CrawlSummary, PageResult},
+    sharded_store::ShardedStore,
+    site_graph::SiteGraph,
+    store::VisitedStore,
+    types::{CrawlerError, Link, LinkKind, ScheduledUrl},
+    url_filter::UrlFilter,
+};
+use reqwest::StatusCode;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::Instant,
 };
-use std::sync::Arc;
 use tokio::{
-    sync::mpsc::{self, Sender},
-    time::sleep,
-    time::Duration,
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Semaphore,
+    },
+    task::{self, JoinHandle, JoinSet},
+    time::{sleep, timeout, Duration},
 };
 use url::Url;
 
 pub struct Crawler;
 
+/// The components shared by every task in a crawl.
+struct Context {
+    html_downloader: HtmlDownloader,
+    html_parser: HtmlParser,
+    url_filter: UrlFilter,
+    config: Config,
+    results: CrawlResults,
+    /// The results of the previous crawl, when re-crawling.
+    previous: Option<CrawlResults>,
+    frontier: Frontier,
+    /// Detects near duplicates, when configured. Also used by `html_parser`, and kept here so
+    /// that its fingerprints can be saved in checkpoints.
+    near_duplicates: Option<Arc<NearDuplicateDetector>>,
+    /// Limits the number of pages fetched at once, when configured.
+    permits: Option<Semaphore>,
+}
+
+/// The URLs which have been scheduled but not yet processed.
+#[derive(Default)]
+struct Frontier {
+    urls: Mutex<HashMap<Url, ScheduledUrl>>,
+}
+
+impl Frontier {
+    fn add(&self, scheduled: ScheduledUrl) {
+        if let Ok(mut urls) = self.urls.lock() {
+            urls.insert(scheduled.url.clone(), scheduled);
+        }
+    }
+
+    fn remove(&self, url: &Url) {
+        if let Ok(mut urls) = self.urls.lock() {
+            urls.remove(url);
+        }
+    }
+
+    fn urls(&self) -> Vec<ScheduledUrl> {
+        match self.urls.lock() {
+            Ok(urls) => urls.values().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.urls.lock().map(|urls| urls.is_empty()).unwrap_or(true)
+    }
+}
+
+type UrlStore = Box<dyn VisitedStore<Url>>;
+type HtmlStore = Box<dyn VisitedStore<String, Option<Url>>>;
+
 impl Crawler {
     /// Given a seed URL, visit each URL in the same domain.
-    pub async fn run(seed: Url) -> Result<(), CrawlerError> {
+    pub async fn run(seed: Url) -> Result<CrawlResults, CrawlerError> {
+        Crawler::run_with_config(seed, Config::default()).await
+    }
+
+    /// Given a seed URL, visit each URL in the same domain using the provided config.
+    pub async fn run_with_config(seed: Url, config: Config) -> Result<CrawlResults, CrawlerError> {
+        let sink = config.format.sink(std::io::stdout());
+        Crawler::crawl(seed, config, None, sink).await
+    }
+
+    /// Given a seed URL, visit each URL in the same domain, writing each page to the sink
+    /// instead of stdout.
+    pub async fn run_with_sink(
+        seed: Url,
+        config: Config,
+        sink: impl OutputSink + 'static,
+    ) -> Result<CrawlResults, CrawlerError> {
+        Crawler::crawl(seed, config, None, Box::new(sink)).await
+    }
+
+    /// Continue the crawl saved in the checkpoint directory. Pages visited before the
+    /// checkpoint are not fetched again, and further checkpoints are saved to the same
+    /// directory unless the config says otherwise.
+    pub async fn resume(dir: PathBuf, config: Config) -> Result<CrawlResults, CrawlerError> {
+        let sink = config.format.sink(std::io::stdout());
+        Crawler::resume_with_sink(dir, config, sink).await
+    }
+
+    /// Continue the crawl saved in the checkpoint directory, writing each page to the sink
+    /// instead of stdout.
+    pub async fn resume_with_sink(
+        dir: PathBuf,
+        mut config: Config,
+        sink: impl OutputSink + 'static,
+    ) -> Result<CrawlResults, CrawlerError> {
+        let checkpoint = Checkpoint::load(&dir)
+            .map_err(|_| CrawlerError::CheckpointError { path: dir.clone() })?;
+        if config.checkpoint.is_none() {
+            config.checkpoint = Some(CheckpointConfig::new(dir));
+        }
+        Crawler::crawl(
+            checkpoint.seed.clone(),
+            config,
+            Some(checkpoint),
+            Box::new(sink),
+        )
+        .await
+    }
+
+    async fn crawl(
+        seed: Url,
+        mut config: Config,
+        checkpoint: Option<Checkpoint>,
+        sink: Box<dyn OutputSink>,
+    ) -> Result<CrawlResults, CrawlerError> {
         let subdomain = seed
             .host_str()
             .ok_or(CrawlerError::SubdomainError { url: seed.clone() })?;
 
-        // Create a channel to schedule URLs. Add the seed URL.
-        let (tx, mut rx) = mpsc::channel(100);
-        tx.send(seed.clone())
-            .await
-            .map_err(|_| CrawlerError::NotScheduled { url: seed.clone() })?;
-
         // Setup components
+        if let Some(path) = &config.netrc {
+            Netrc::load(path)
+                .map_err(|_| CrawlerError::NetrcError { path: path.clone() })?
+                .apply(&mut config.hosts);
+        }
         let robots_url = seed.join("robots.txt")?;
-        let html_downloader = Arc::new(HtmlDownloader::default());
-        let robots_txt = html_downloader.fetch(robots_url).await.unwrap();
-        let html_parser = Arc::new(HtmlParser::default());
-        let url_filter = Arc::new(UrlFilter::new(subdomain.to_string(), robots_txt.body));
+        let html_downloader = HtmlDownloader::new(&config.client)?
+            .with_hosts(&config.hosts)
+            .with_scope(config.scope, &seed);
+        if let Some(path) = &config.cookies {
+            html_downloader
+                .cookies()
+                .load(path)
+                .map_err(|_| CrawlerError::CookiesError { path: path.clone() })?;
+        }
+        if let Some(login) = &config.login {
+            Login::submit(&html_downloader, login).await?;
+        }
+        let robots_txt = html_downloader
+            .fetch(robots_url)
+            .await
+            .map_err(|error| CrawlerError::ClientError(error.to_string()))?;
+        // Everything is allowed when there is no robots.txt.
+        let robots_txt = if robots_txt.status.is_success() {
+            robots_txt.body
+        } else {
+            String::new()
+        };
+        let (url_store, html_store) = Crawler::build_stores(&config.store, checkpoint.is_some())?;
+        let results = CrawlResults::new();
+        let previous = match &config.previous_results {
+            Some(path) => Some(
+                CrawlResults::load(path)
+                    .map_err(|_| CrawlerError::ResultsError { path: path.clone() })?,
+            ),
+            None => None,
+        };
+
+        // Restore the progress saved in the checkpoint, otherwise start from the seed URL.
+        let near_duplicates = config
+            .near_duplicate_threshold
+            .map(|threshold| Arc::new(NearDuplicateDetector::new(threshold)));
+        let frontier = match checkpoint {
+            Some(checkpoint) => {
+                if let Some(detector) = &near_duplicates {
+                    for (fingerprint, url) in checkpoint.fingerprints {
+                        detector.insert(fingerprint, &url);
+                    }
+                }
+                for url in checkpoint.visited {
+                    url_store.insert(url);
+                }
+                for (hash, url) in checkpoint.hashes {
+                    html_store.insert_or_get(hash, Some(url));
+                }
+                for page in checkpoint.pages {
+                    results.insert(page);
+                }
+                checkpoint.frontier
+            }
+            None => {
+                url_store.insert(seed.clone());
+                vec![ScheduledUrl::seed(seed.clone())]
+            }
+        };
+
+        let mut html_parser = HtmlParser::new(html_store);
+        if let Some(detector) = &near_duplicates {
+            html_parser = html_parser.with_detector(detector.clone());
+        }
+        let mut url_filter = UrlFilter::new(subdomain.to_string(), robots_txt)
+            .with_scope(config.scope, &seed)
+            .with_user_agent(&config.client.user_agent)
+            .with_hosts(&config.hosts)
+            .with_store(url_store);
+        if config.login.is_some() {
+            url_filter = url_filter.with_logout_avoided();
+        }
+        let context = Arc::new(Context {
+            html_downloader,
+            html_parser,
+            url_filter,
+            permits: config.concurrency.map(Semaphore::new),
+            config,
+            results,
+            previous,
+            frontier: Frontier::default(),
+            near_duplicates,
+        });
+
+        // Create a channel to schedule URLs. Add the frontier in the background, as it may not
+        // fit in the channel.
+        let (tx, mut rx) = mpsc::channel(100);
+        for scheduled in &frontier {
+            context.frontier.add(scheduled.clone());
+        }
+        let url_scheduler = tx.clone();
+        tokio::spawn(async move {
+            for scheduled in frontier {
+                let _ = url_scheduler.send(scheduled).await;
+            }
+        });
+
+        // Pages are written by a single task, so output from concurrent tasks isn't interleaved.
+        let sink = SortedSink::wrap(sink, context.config.order);
+        let (output, writer) = Crawler::spawn_writer(sink);
+
+        // Stop taking new URLs once the caller triggers a shutdown.
+        let shutdown = context.config.shutdown.clone();
 
         // Event loop
+        let started = Instant::now();
+        let mut last_checkpoint = Instant::now();
+        let mut tasks = JoinSet::new();
         loop {
+            if shutdown.is_triggered() {
+                Crawler::finish_in_progress(&mut tasks, &mut rx, shutdown.timeout).await;
+                break;
+            }
+            while tasks.try_join_next().is_some() {}
+
+            if let Some(checkpoint) = &context.config.checkpoint {
+                if last_checkpoint.elapsed() >= checkpoint.interval {
+                    Crawler::save_checkpoint(&seed, &context, checkpoint);
+                    last_checkpoint = Instant::now();
+                }
+            }
+
             match rx.try_recv() {
-                Ok(url) => {
-                    Crawler::process(
-                        url,
-                        html_downloader.clone(),
-                        html_parser.clone(),
-                        url_filter.clone(),
+                Ok(scheduled) => {
+                    tasks.spawn(Crawler::process(
+                        scheduled,
+                        context.clone(),
                         tx.clone(),
-                    );
+                        output.clone(),
+                    ));
                 }
                 Err(_) => {
-                    // Wait for crawls to complete.
-                    sleep(Duration::from_secs(1)).await;
-                    if rx.is_empty() {
+                    // Finished once every scheduled URL has been processed.
+                    if context.frontier.is_empty() {
                         break;
                     }
+                    // Wait for crawls to complete.
+                    sleep(Duration::from_millis(100)).await;
+                }
+            }
+        }
+
+        // Flush the output once every task has dropped its sender, then save the progress,
+        // including any URLs left unvisited.
+        drop(tasks);
+        drop(output);
+        let written = writer
+            .await
+            .unwrap_or_else(|error| Err(std::io::Error::other(error)));
+        if let Some(checkpoint) = &context.config.checkpoint {
+            Crawler::save_checkpoint(&seed, &context, checkpoint);
+        }
+        // Files which can't be written are reported once the summary is set.
+        let mut failures = Vec::new();
+        if let Some(path) = &context.config.save_results {
+            if let Err(error) = context.results.save(path) {
+                failures.push(format!("unable to save results to {:?}: {}", path, error));
+            }
+        }
+        if let Some(path) = &context.config.cookies {
+            if let Err(error) = context.html_downloader.cookies().save(path) {
+                failures.push(format!("unable to save cookies to {:?}: {}", path, error));
+            }
+        }
+        if let Some(path) = &context.config.export_edges {
+            if let Err(error) = EdgeList::save_edges(path, &context.results) {
+                failures.push(format!("unable to export edges to {:?}: {}", path, error));
+            }
+        }
+        if let Some(path) = &context.config.export_nodes {
+            if let Err(error) = EdgeList::save_nodes(path, &context.results) {
+                failures.push(format!("unable to export nodes to {:?}: {}", path, error));
+            }
+        }
+        if let Some(path) = &context.config.export_graph {
+            let graph = SiteGraph::new(&context.results, context.config.collapse_depth);
+            if let Err(error) = graph.save(path) {
+                failures.push(format!(
+                    "unable to export the graph to {:?}: {}",
+                    path, error
+                ));
+            }
+        }
+        let mut summary = CrawlSummary {
+            unvisited: context.frontier.urls().len(),
+            elapsed: started.elapsed(),
+            interrupted: shutdown.is_triggered(),
+            audit_issues: Vec::new(),
+            external_links: BTreeMap::new(),
+        };
+        if context.config.check_external_links && !summary.interrupted {
+            summary.external_links = Crawler::check_external_links(&context).await;
+        }
+        if context.config.audit && !summary.interrupted {
+            summary.audit_issues = Auditor::new(&context.results, &context.html_downloader)
+                .audit()
+                .await;
+        }
+        context.results.set_summary(summary);
+
+        written.map_err(|error| CrawlerError::OutputError(error.to_string()))?;
+        if !failures.is_empty() {
+            return Err(CrawlerError::OutputError(failures.join("; ")));
+        }
+        Ok(context.results.clone())
+    }
+
+    /// Wait for the pages in progress to finish, giving up on any still running after the
+    /// timeout. Newly scheduled URLs are taken off the channel so that tasks waiting to
+    /// schedule links aren't blocked, but are left in the frontier unvisited.
+    async fn finish_in_progress(
+        tasks: &mut JoinSet<()>,
+        url_scheduler: &mut Receiver<ScheduledUrl>,
+        wait: Duration,
+    ) {
+        let finished = timeout(wait, async {
+            loop {
+                tokio::select! {
+                    task = tasks.join_next() => if task.is_none() { break },
+                    _ = url_scheduler.recv() => {}
+                }
+            }
+        })
+        .await;
+        if finished.is_err() {
+            tasks.abort_all();
+        }
+    }
+
+    /// Start a task which writes each page sent to it to the sink, returning the first error
+    /// once every sender is dropped. Pages are still received after an error, so that senders
+    /// aren't blocked, but aren't written.
+    fn spawn_writer(
+        mut sink: Box<dyn OutputSink>,
+    ) -> (Sender<PageResult>, JoinHandle<std::io::Result<()>>) {
+        let (tx, mut rx) = mpsc::channel::<PageResult>(100);
+        let writer = task::spawn_blocking(move || {
+            let mut result = Ok(());
+            while let Some(page) = rx.blocking_recv() {
+                if result.is_ok() {
+                    result = sink.write(&page);
                 }
             }
+            result.and_then(|_| sink.finish())
+        });
+        (tx, writer)
+    }
+
+    /// Create the stores for visited URLs and page hashes. Stores kept on disk are emptied,
+    /// unless the crawl is being resumed.
+    fn build_stores(
+        backend: &StoreBackend,
+        resuming: bool,
+    ) -> Result<(UrlStore, HtmlStore), CrawlerError> {
+        match backend {
+            StoreBackend::Memory => Ok((
+                Box::new(ShardedStore::default()),
+                Box::new(ShardedStore::default()),
+            )),
+            StoreBackend::Disk(path) => {
+                let store_error = |_| CrawlerError::StoreUnavailable { path: path.clone() };
+                let db = sled::open(path).map_err(store_error)?;
+                let urls = DiskStore::open_tree(&db, "urls").map_err(store_error)?;
+                let hashes = DiskStore::open_tree(&db, "hashes").map_err(store_error)?;
+                if !resuming {
+                    urls.clear().map_err(store_error)?;
+                    hashes.clear().map_err(store_error)?;
+                }
+                Ok((Box::new(urls), Box::new(hashes)))
+            }
+            StoreBackend::Bloom {
+                expected_items,
+                false_positive_rate,
+            } => Ok((
+                Box::new(BloomStore::new(*expected_items, *false_positive_rate)),
+                Box::new(ShardedStore::default()),
+            )),
         }
+    }
 
-        Ok(())
+    /// Save the progress of the crawl. Failures are reported but don't stop the crawl.
+    fn save_checkpoint(seed: &Url, context: &Context, config: &CheckpointConfig) {
+        let mut checkpoint =
+            Checkpoint::new(seed.clone(), context.frontier.urls(), &context.results);
+        if let Some(detector) = &context.near_duplicates {
+            checkpoint = checkpoint.with_fingerprints(detector.fingerprints());
+        }
+        if let Err(error) = checkpoint.save(&config.dir) {
+            eprintln!("Unable to save checkpoint to {:?}: {}", config.dir, error);
+        }
     }
 
-    /// Spawn a task to fetch, parse, and schedule new URLs to be crawled.
-    fn process(
-        url: Url,
-        html_downloader: Arc<HtmlDownloader>,
-        html_parser: Arc<HtmlParser>,
-        url_filter: Arc<UrlFilter>,
-        url_scheduler: Sender<Url>,
+    /// Fetch, parse, and schedule new URLs to be crawled. Run as a task for each URL.
+    async fn process(
+        scheduled: ScheduledUrl,
+        context: Arc<Context>,
+        url_scheduler: Sender<ScheduledUrl>,
+        output: Sender<PageResult>,
     ) {
-        tokio::spawn(async move {
-            if let Ok(page) = html_downloader.fetch(url.clone()).await {
-                if page.status.is_success() {
-                    let links = html_parser.parse(page.body);
+        let url = &scheduled.url;
 
-                    // Print the links found at this URL
-                    let _ = Printer::print(std::io::stdout(), url, &links);
+        // A page the previous crawl found to duplicate another isn't fetched again. Its
+        // previous result is kept and its canonical URL is crawled instead.
+        if let Some(duplicate) = Crawler::known_duplicate(&scheduled, &context) {
+            let links = duplicate
+                .canonical
+                .iter()
+                .map(|canonical| Link::new(canonical.clone(), LinkKind::Navigation))
+                .collect();
+            context.results.insert(duplicate);
+            Crawler::schedule(&scheduled, links, &context, &url_scheduler).await;
+            context.frontier.remove(url);
+            return;
+        }
 
-                    // Filter links and add them to the scheduler
-                    for link in url_filter.filter(links) {
-                        let _ = url_scheduler.send(link).await;
-                    }
+        let permit = match &context.permits {
+            Some(permits) => permits.acquire().await.ok(),
+            None => None,
+        };
+        let result = Crawler::visit(&scheduled, &context).await;
+        drop(permit);
+
+        // Output the page, unless it asked not to be indexed.
+        let config = &context.config;
+        if config.ignore_robots_directives || !result.robots.noindex {
+            let _ = output.send(result.clone()).await;
+        }
+
+        if result.is_success() {
+            // Drop links the page asked not to be followed.
+            let mut links: HashSet<_> = if config.ignore_robots_directives {
+                result.links.clone()
+            } else if result.robots.nofollow {
+                HashSet::new()
+            } else {
+                result
+                    .links
+                    .iter()
+                    .filter(|link| !link.nofollow)
+                    .cloned()
+                    .collect()
+            };
+
+            // A duplicate of another page is crawled through its canonical URL instead.
+            if config.dedupe_by_canonical {
+                if let Some(canonical) = result.canonical.clone().filter(|c| c != url) {
+                    links = HashSet::from([Link::new(canonical, LinkKind::Navigation)]);
                 }
             }
-        });
+
+            context.results.insert(result);
+            Crawler::schedule(&scheduled, links, &context, &url_scheduler).await;
+        } else {
+            context.results.insert(result);
+        }
+
+        // Remove the URL once its links are scheduled, so the frontier is never empty
+        // while there is more to crawl.
+        context.frontier.remove(url);
+    }
+
+    /// Filter the links found on the page and add them to the scheduler. Links on pages at
+    /// the maximum depth are reported but not followed.
+    async fn schedule(
+        scheduled: &ScheduledUrl,
+        links: HashSet<Link>,
+        context: &Context,
+        url_scheduler: &Sender<ScheduledUrl>,
+    ) {
+        let max_depth = context.config.max_depth;
+        if max_depth.is_some_and(|max| scheduled.depth >= max) {
+            return;
+        }
+        Crawler::fetch_robots_txt(context, &links).await;
+        for link in context.url_filter.filter(links) {
+            let link = scheduled.link(link);
+            context.frontier.add(link.clone());
+            let _ = url_scheduler.send(link).await;
+        }
+    }
+
+    /// Return the previous result of the URL if, with `Config::dedupe_by_canonical`, the
+    /// previous crawl found it to declare a different canonical URL.
+    fn known_duplicate(scheduled: &ScheduledUrl, context: &Context) -> Option<PageResult> {
+        if !context.config.dedupe_by_canonical {
+            return None;
+        }
+        let mut previous = context.previous.as_ref()?.get(&scheduled.url)?;
+        if previous
+            .canonical
+            .as_ref()
+            .is_none_or(|canonical| *canonical == scheduled.url)
+        {
+            return None;
+        }
+        previous.changed = None;
+        previous.depth = scheduled.depth;
+        previous.referrer = scheduled.referrer.clone();
+        Some(previous)
+    }
+
+    /// Request the target of each link to another host once, without following its links,
+    /// returning the status of each or `None` if it couldn't be reached. Assets and embedded
+    /// documents are checked along with navigation links.
+    async fn check_external_links(context: &Arc<Context>) -> BTreeMap<Url, Option<StatusCode>> {
+        let targets: BTreeSet<Url> = context
+            .results
+            .pages()
+            .into_iter()
+            .flat_map(|page| page.links)
+            .filter(|link| link.kind != LinkKind::Form)
+            .map(|link| link.url)
+            .filter(|url| matches!(url.scheme(), "http" | "https"))
+            .filter(|url| context.url_filter.is_external(url))
+            .collect();
+
+        let mut tasks = JoinSet::new();
+        for url in targets {
+            let context = context.clone();
+            tasks.spawn(async move {
+                let permit = match &context.permits {
+                    Some(permits) => permits.acquire().await.ok(),
+                    None => None,
+                };
+                let status = context.html_downloader.check(url.clone()).await.ok();
+                drop(permit);
+                (url, status.map(|page| page.status))
+            });
+        }
+        let mut statuses = BTreeMap::new();
+        while let Some(task) = tasks.join_next().await {
+            if let Ok((url, status)) = task {
+                statuses.insert(url, status);
+            }
+        }
+        statuses
+    }
+
+    /// Fetch the robots.txt of each host linked to which is in scope but hasn't been fetched
+    /// yet, for links to it to be checked against. A host without one, or which can't be
+    /// reached, allows everything.
+    async fn fetch_robots_txt(context: &Context, links: &HashSet<Link>) {
+        let robots_urls: HashMap<String, Url> = links
+            .iter()
+            .filter(|link| context.url_filter.needs_robots_txt(&link.url))
+            .filter_map(|link| {
+                let host = link.url.host_str()?.to_string();
+                Some((host, link.url.join("/robots.txt").ok()?))
+            })
+            .collect();
+        for (host, url) in robots_urls {
+            let robots_txt = match context.html_downloader.fetch(url).await {
+                Ok(page) if page.status.is_success() => page.body,
+                _ => String::new(),
+            };
+            context.url_filter.add_robots_txt(&host, robots_txt);
+        }
+    }
+
+    /// Why a request failed, in a few words.
+    fn describe(error: &reqwest::Error) -> String {
+        if error.is_timeout() {
+            "timed out".to_string()
+        } else if error.is_connect() {
+            "couldn't connect".to_string()
+        } else {
+            error.to_string()
+        }
+    }
+
+    /// Fetch and parse the URL. A page which hasn't been modified since the previous crawl
+    /// reuses the result from then, and a page which couldn't be fetched records why.
+    async fn visit(scheduled: &ScheduledUrl, context: &Context) -> PageResult {
+        let url = &scheduled.url;
+        let previous = context
+            .previous
+            .as_ref()
+            .and_then(|results| results.get(url));
+        let validators = previous
+            .as_ref()
+            .map(PageResult::validators)
+            .unwrap_or_default();
+        let page = match context
+            .html_downloader
+            .fetch_conditional(url.clone(), &validators)
+            .await
+        {
+            Ok(page) => page,
+            Err(error) => {
+                let mut result = PageResult::failed(url.clone(), Crawler::describe(&error));
+                result.depth = scheduled.depth;
+                result.referrer = scheduled.referrer.clone();
+                return result;
+            }
+        };
+
+        if page.status == StatusCode::NOT_MODIFIED {
+            if let Some(mut previous) = previous {
+                previous.changed = Some(false);
+                previous.depth = scheduled.depth;
+                previous.referrer = scheduled.referrer.clone();
+                previous.response_time_ms = page.elapsed.as_millis() as u64;
+                if let Some(content_hash) = &previous.content_hash {
+                    previous.duplicate_of = context.html_parser.record_unchanged(url, content_hash);
+                }
+                return previous;
+            }
+        }
+
+        let mut result = PageResult::new(url.clone(), page.url.clone(), page.status);
+        result.etag = page.validators.etag;
+        result.last_modified = page.validators.last_modified;
+        result.depth = scheduled.depth;
+        result.referrer = scheduled.referrer.clone();
+        result.content_type = page.content_type;
+        result.response_time_ms = page.elapsed.as_millis() as u64;
+        result.size = page.body.len();
+        if page.status.is_success() {
+            result.content_hash = Some(ExactDuplicateDetector::calculate_hash(&page.body));
+            let parsed = context.html_parser.parse(url, &page.url, page.body);
+            result.robots = page.robots.merge(parsed.robots);
+            result.links = parsed.links;
+            result.canonical = parsed.canonical;
+            result.hreflang = parsed.hreflang;
+            result.duplicate_of = parsed.duplicate_of;
+        }
+        if context.previous.is_some() {
+            let previous_hash = previous.and_then(|previous| previous.content_hash);
+            result.changed =
+                Some(result.content_hash.is_none() || result.content_hash != previous_hash);
+        }
+        result
     }
 }
@@ -0,0 +1,141 @@
+use crate::results::{CrawlResults, PageResult};
+use reqwest::StatusCode;
+use std::collections::{BTreeMap, BTreeSet};
+use url::Url;
+
+/// The differences between two crawls of the same site.
+#[derive(Debug, Default, PartialEq)]
+pub struct CrawlDiff {
+    /// Pages only visited by the new crawl.
+    pub added: BTreeSet<Url>,
+    /// Pages only visited by the old crawl.
+    pub removed: BTreeSet<Url>,
+    /// Pages whose status code changed, with the old and new status.
+    /// A page which couldn't be fetched has no status.
+    pub status_changes: BTreeMap<Url, (Option<StatusCode>, Option<StatusCode>)>,
+    /// Pages whose content hash changed.
+    pub content_changes: BTreeSet<Url>,
+    /// Links, as (page, link) pairs, only found by the new crawl.
+    pub added_edges: BTreeSet<(Url, Url)>,
+    /// Links, as (page, link) pairs, only found by the old crawl.
+    pub removed_edges: BTreeSet<(Url, Url)>,
+}
+
+impl CrawlDiff {
+    /// Compare the results of an old crawl with a new one.
+    pub fn between(old: &CrawlResults, new: &CrawlResults) -> Self {
+        let old = Self::by_url(old);
+        let new = Self::by_url(new);
+        let mut diff = CrawlDiff {
+            added: new
+                .keys()
+                .filter(|url| !old.contains_key(*url))
+                .cloned()
+                .collect(),
+            removed: old
+                .keys()
+                .filter(|url| !new.contains_key(*url))
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+
+        for (url, new_page) in &new {
+            let Some(old_page) = old.get(url) else {
+                continue;
+            };
+            if old_page.status != new_page.status {
+                diff.status_changes
+                    .insert(url.clone(), (old_page.status, new_page.status));
+            }
+            if old_page.content_hash.is_some()
+                && new_page.content_hash.is_some()
+                && old_page.content_hash != new_page.content_hash
+            {
+                diff.content_changes.insert(url.clone());
+            }
+        }
+
+        let old_edges = Self::edges(&old);
+        let new_edges = Self::edges(&new);
+        diff.added_edges = new_edges.difference(&old_edges).cloned().collect();
+        diff.removed_edges = old_edges.difference(&new_edges).cloned().collect();
+        diff
+    }
+
+    /// Determine whether the crawls found the same pages, statuses, content and links.
+    pub fn is_empty(&self) -> bool {
+        *self == CrawlDiff::default()
+    }
+
+    fn by_url(results: &CrawlResults) -> BTreeMap<Url, PageResult> {
+        results
+            .pages()
+            .into_iter()
+            .map(|page| (page.url.clone(), page))
+            .collect()
+    }
+
+    fn edges(pages: &BTreeMap<Url, PageResult>) -> BTreeSet<(Url, Url)> {
+        pages
+            .values()
+            .flat_map(|page| {
+                page.links
+                    .iter()
+                    .map(|link| (page.url.clone(), link.url.clone()))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::CrawlDiff;
+    use crate::{
+        results::{CrawlResults, PageResult},
+        types::{Link, LinkKind},
+    };
+    use reqwest::StatusCode;
+    use url::Url;
+
+    fn page(url: &Url, status: StatusCode, hash: &str, links: &[&Url]) -> PageResult {
+        let mut page = PageResult::new(url.clone(), url.clone(), status);
+        page.content_hash = Some(hash.to_string());
+        page.links = links
+            .iter()
+            .map(|link| Link::new((*link).clone(), LinkKind::Navigation))
+            .collect();
+        page
+    }
+
+    #[test]
+    fn diff() {
+        let base = Url::parse("https://example.com/").unwrap();
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|p| base.join(p).unwrap());
+
+        let old = CrawlResults::new();
+        old.insert(page(&base, StatusCode::OK, "1", &[&a, &b]));
+        old.insert(page(&a, StatusCode::OK, "2", &[]));
+        old.insert(page(&b, StatusCode::OK, "3", &[]));
+        old.insert(page(&c, StatusCode::OK, "4", &[]));
+
+        let new = CrawlResults::new();
+        new.insert(page(&base, StatusCode::OK, "1", &[&a, &d]));
+        new.insert(page(&a, StatusCode::OK, "5", &[]));
+        new.insert(page(&b, StatusCode::NOT_FOUND, "3", &[]));
+        new.insert(page(&d, StatusCode::OK, "6", &[]));
+
+        let diff = CrawlDiff::between(&old, &new);
+        assert_eq!(diff.added.iter().collect::<Vec<_>>(), vec![&d]);
+        assert_eq!(diff.removed.iter().collect::<Vec<_>>(), vec![&c]);
+        assert_eq!(
+            diff.status_changes.get(&b),
+            Some(&(Some(StatusCode::OK), Some(StatusCode::NOT_FOUND)))
+        );
+        assert_eq!(diff.content_changes.iter().collect::<Vec<_>>(), vec![&a]);
+        assert!(diff.added_edges.contains(&(base.clone(), d.clone())));
+        assert!(diff.removed_edges.contains(&(base.clone(), b.clone())));
+        assert!(!diff.is_empty());
+        assert!(CrawlDiff::between(&old, &old).is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+use crate::store::VisitedStore;
+use std::{marker::PhantomData, path::Path};
+use url::Url;
+
+/// Items and values which can be written to a persistent store as bytes.
+pub trait Encode: Sized {
+    fn encode(&self) -> Vec<u8>;
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+impl Encode for () {
+    fn encode(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn decode(_bytes: &[u8]) -> Option<Self> {
+        Some(())
+    }
+}
+
+impl Encode for String {
+    fn encode(&self) -> Vec<u8> {
+        self.as_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+impl Encode for Url {
+    fn encode(&self) -> Vec<u8> {
+        self.as_str().as_bytes().to_vec()
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Url::parse(std::str::from_utf8(bytes).ok()?).ok()
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    /// A present value is prefixed with a marker byte, so that it can be told apart from
+    /// `None` even when it encodes to nothing.
+    fn encode(&self) -> Vec<u8> {
+        match self {
+            Some(value) => [vec![1], value.encode()].concat(),
+            None => Vec::new(),
+        }
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes.split_first() {
+            Some((1, value)) => Some(Some(T::decode(value)?)),
+            _ => Some(None),
+        }
+    }
+}
+
+/// A store kept on disk in an embedded key-value database, so that very large crawls don't
+/// need every visited item in memory. Stores opened on the same database with different
+/// names are kept separate.
+pub struct DiskStore<T, V = ()> {
+    tree: sled::Tree,
+    marker: PhantomData<fn(T, V)>,
+}
+
+impl<T, V> DiskStore<T, V> {
+    /// Open the store called `name` in the database at `path`, creating it if required.
+    pub fn open(path: &Path, name: &str) -> sled::Result<Self> {
+        let db = sled::open(path)?;
+        Ok(DiskStore {
+            tree: db.open_tree(name)?,
+            marker: PhantomData,
+        })
+    }
+
+    /// Open the store called `name` in an already open database.
+    pub fn open_tree(db: &sled::Db, name: &str) -> sled::Result<Self> {
+        Ok(DiskStore {
+            tree: db.open_tree(name)?,
+            marker: PhantomData,
+        })
+    }
+
+    /// Remove every item, so that a new crawl doesn't see the items of an earlier one.
+    pub fn clear(&self) -> sled::Result<()> {
+        self.tree.clear()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tree.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tree.is_empty()
+    }
+}
+
+impl<T: Encode, V: Encode> VisitedStore<T, V> for DiskStore<T, V> {
+    fn insert_or_get(&self, item: T, value: V) -> Option<V> {
+        // Only write the value if the item isn't already present.
+        match self
+            .tree
+            .compare_and_swap(item.encode(), None as Option<&[u8]>, Some(value.encode()))
+        {
+            Ok(Ok(())) => None,
+            Ok(Err(existing)) => existing.current.and_then(|bytes| V::decode(&bytes)),
+            Err(error) => {
+                eprintln!("DiskStore write failed: {}", error);
+                // Report the item as already seen so it isn't processed again.
+                Some(value)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DiskStore;
+    use crate::store::VisitedStore;
+    use url::Url;
+
+    #[test]
+    fn insert() {
+        let dir = tempfile::tempdir().unwrap();
+        let store: DiskStore<String> = DiskStore::open(dir.path(), "items").unwrap();
+        assert!(store.insert("a".to_string()));
+        assert!(store.insert("b".to_string()));
+        assert!(!store.insert("a".to_string()));
+        assert_eq!(store.len(), 2);
+    }
+
+    #[test]
+    fn insert_or_get_persists() {
+        let dir = tempfile::tempdir().unwrap();
+        let url = Url::parse("https://example.com/").unwrap();
+        {
+            let store = DiskStore::open(dir.path(), "hashes").unwrap();
+            assert_eq!(
+                store.insert_or_get("abc".to_string(), Some(url.clone())),
+                None
+            );
+            assert_eq!(store.insert_or_get("def".to_string(), None), None);
+        }
+
+        // Reopening the database keeps the values from before.
+        let store: DiskStore<String, Option<Url>> = DiskStore::open(dir.path(), "hashes").unwrap();
+        assert_eq!(
+            store.insert_or_get("abc".to_string(), None),
+            Some(Some(url))
+        );
+        assert_eq!(store.insert_or_get("def".to_string(), None), Some(None));
+
+        store.clear().unwrap();
+        assert!(store.is_empty());
+        assert_eq!(store.insert_or_get("abc".to_string(), None), None);
+    }
+}
@@ -0,0 +1,365 @@
+use crate::{sharded_store::ShardedStore, store::VisitedStore};
+use scraper::{Html, Node};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use url::Url;
+
+/// Detects pages whose content has been seen before.
+pub trait DuplicateDetector: Send + Sync {
+    /// Record the page, returning the URL of the earlier page it duplicates if there is one.
+    fn check(&self, url: &Url, body: &str, html: &Html) -> Option<Url>;
+
+    /// Record a page which wasn't modified since the previous crawl, by the hash of its body
+    /// from then, returning the URL of the earlier page it duplicates if there is one.
+    /// Detectors which need the body record nothing.
+    fn check_hash(&self, _url: &Url, _content_hash: &str) -> Option<Url> {
+        None
+    }
+}
+
+impl<D: DuplicateDetector> DuplicateDetector for Arc<D> {
+    fn check(&self, url: &Url, body: &str, html: &Html) -> Option<Url> {
+        (**self).check(url, body, html)
+    }
+
+    fn check_hash(&self, url: &Url, content_hash: &str) -> Option<Url> {
+        (**self).check_hash(url, content_hash)
+    }
+}
+
+/// Detects pages whose raw body is identical to an earlier page, using a SHA-256 hash.
+pub struct ExactDuplicateDetector {
+    html_store: Box<dyn VisitedStore<String, Option<Url>>>,
+}
+
+impl Default for ExactDuplicateDetector {
+    fn default() -> Self {
+        Self::new(ShardedStore::default())
+    }
+}
+
+impl ExactDuplicateDetector {
+    /// Create a detector which keeps the hashes of pages, and the URL each was first seen
+    /// at, in the given store.
+    pub fn new(html_store: impl VisitedStore<String, Option<Url>> + 'static) -> Self {
+        ExactDuplicateDetector {
+            html_store: Box::new(html_store),
+        }
+    }
+
+    /// Calculate the SHA-256 hash of the body.
+    pub fn calculate_hash(body: &str) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(body);
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+impl DuplicateDetector for ExactDuplicateDetector {
+    fn check(&self, url: &Url, body: &str, _html: &Html) -> Option<Url> {
+        // Store this page's hash for future comparisons.
+        self.html_store
+            .insert_or_get(Self::calculate_hash(body), Some(url.clone()))
+            .flatten()
+    }
+
+    fn check_hash(&self, url: &Url, content_hash: &str) -> Option<Url> {
+        self.html_store
+            .insert_or_get(content_hash.to_string(), Some(url.clone()))
+            .flatten()
+    }
+}
+
+/// Detects pages whose visible text is nearly identical to an earlier page, so that pages
+/// differing only by a timestamp, token or advert are still matched. Each page is reduced to
+/// a 64-bit SimHash of its word shingles, and two pages are duplicates when the fraction of
+/// matching bits is at least the similarity threshold.
+///
+/// Fingerprints are split into `max_distance + 1` bands of bits. Two fingerprints differing
+/// by at most `max_distance` bits must agree on a whole band, so a page is only compared with
+/// the earlier pages sharing one of its bands rather than with every earlier page.
+pub struct NearDuplicateDetector {
+    max_distance: u32,
+    /// The mask selecting the bits of each band.
+    bands: Vec<u64>,
+    fingerprints: Mutex<Fingerprints>,
+}
+
+/// The fingerprints of the pages which weren't near duplicates, indexed by band.
+#[derive(Default)]
+struct Fingerprints {
+    /// Each fingerprint and the URL it was first seen at, in the order seen.
+    seen: Vec<(u64, Url)>,
+    /// For each band, the positions in `seen` of the fingerprints with each value of its bits.
+    bands: Vec<HashMap<u64, Vec<usize>>>,
+}
+
+/// The number of consecutive words hashed together as one feature.
+const SHINGLE_SIZE: usize = 3;
+
+impl NearDuplicateDetector {
+    /// Create a detector with a similarity threshold between 0.0 and 1.0, where 1.0 only
+    /// matches pages with identical fingerprints.
+    pub fn new(threshold: f64) -> Self {
+        let threshold = threshold.clamp(0.0, 1.0);
+        let max_distance = ((1.0 - threshold) * 64.0).round() as u32;
+        let bands = Self::bands(max_distance);
+        NearDuplicateDetector {
+            max_distance,
+            fingerprints: Mutex::new(Fingerprints {
+                seen: Vec::new(),
+                bands: vec![HashMap::new(); bands.len()],
+            }),
+            bands,
+        }
+    }
+
+    /// Split the 64 bits into `max_distance + 1` bands of nearly equal width. Every pair of
+    /// fingerprints is within a distance of 64, so then a single empty band matches them all.
+    fn bands(max_distance: u32) -> Vec<u64> {
+        if max_distance >= 64 {
+            return vec![0];
+        }
+        let count = max_distance as u64 + 1;
+        (0..count)
+            .map(|band| {
+                let (start, end) = (64 * band / count, 64 * (band + 1) / count);
+                let width = end - start;
+                let bits = if width == 64 {
+                    u64::MAX
+                } else {
+                    (1 << width) - 1
+                };
+                bits << start
+            })
+            .collect()
+    }
+
+    /// The fingerprint of each page which wasn't a near duplicate, along with its URL, in the
+    /// order seen. For example to save in a checkpoint.
+    pub fn fingerprints(&self) -> Vec<(u64, Url)> {
+        match self.fingerprints.lock() {
+            Ok(fingerprints) => fingerprints.seen.clone(),
+            Err(_) => {
+                eprintln!("NearDuplicateDetector lock poisioned");
+                Vec::new()
+            }
+        }
+    }
+
+    /// Record the fingerprint of a page seen before, for example restored from a checkpoint,
+    /// returning the URL of the earlier page it duplicates if there is one.
+    pub fn insert(&self, fingerprint: u64, url: &Url) -> Option<Url> {
+        let mut fingerprints = match self.fingerprints.lock() {
+            Ok(fingerprints) => fingerprints,
+            Err(_) => {
+                eprintln!("NearDuplicateDetector lock poisioned");
+                return None;
+            }
+        };
+
+        // The earliest page within the distance, among those sharing a band.
+        let matched = self
+            .bands
+            .iter()
+            .zip(&fingerprints.bands)
+            .filter_map(|(mask, band)| band.get(&(fingerprint & mask)))
+            .flatten()
+            .filter(|position| {
+                let (other, _) = &fingerprints.seen[**position];
+                (fingerprint ^ other).count_ones() <= self.max_distance
+            })
+            .min()
+            .map(|position| fingerprints.seen[*position].1.clone());
+        if matched.is_none() {
+            let position = fingerprints.seen.len();
+            fingerprints.seen.push((fingerprint, url.clone()));
+            for (mask, band) in self.bands.iter().zip(&mut fingerprints.bands) {
+                band.entry(fingerprint & mask).or_default().push(position);
+            }
+        }
+        matched
+    }
+
+    /// Return the words of the text a user would see, skipping scripts and styles.
+    fn visible_words(html: &Html) -> Vec<String> {
+        let mut words = Vec::new();
+        for node in html.root_element().descendants() {
+            let Node::Text(text) = node.value() else {
+                continue;
+            };
+            let hidden = node.ancestors().any(|ancestor| {
+                ancestor.value().as_element().is_some_and(|element| {
+                    matches!(element.name(), "script" | "style" | "noscript" | "template")
+                })
+            });
+            if hidden {
+                continue;
+            }
+            words.extend(
+                text.split(|c: char| !c.is_alphanumeric())
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_lowercase),
+            );
+        }
+        words
+    }
+
+    /// Calculate the SimHash of the page's visible text. Returns `None` when there is no text.
+    pub fn fingerprint(html: &Html) -> Option<u64> {
+        let words = Self::visible_words(html);
+        if words.is_empty() {
+            return None;
+        }
+
+        let mut weights = [0i64; 64];
+        for shingle in words.windows(SHINGLE_SIZE.min(words.len())) {
+            let hash = fnv1a(shingle.join(" ").as_bytes());
+            for (bit, weight) in weights.iter_mut().enumerate() {
+                if hash & (1 << bit) != 0 {
+                    *weight += 1;
+                } else {
+                    *weight -= 1;
+                }
+            }
+        }
+
+        Some(
+            weights
+                .iter()
+                .enumerate()
+                .filter(|(_, weight)| **weight > 0)
+                .fold(0, |fingerprint, (bit, _)| fingerprint | (1 << bit)),
+        )
+    }
+}
+
+impl DuplicateDetector for NearDuplicateDetector {
+    fn check(&self, url: &Url, _body: &str, html: &Html) -> Option<Url> {
+        self.insert(Self::fingerprint(html)?, url)
+    }
+}
+
+/// The 64-bit FNV-1a hash, which is stable across runs and platforms.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DuplicateDetector, ExactDuplicateDetector, NearDuplicateDetector};
+    use scraper::Html;
+    use url::Url;
+
+    const ARTICLE: &str = "The quick brown fox jumps over the lazy dog while the farmer \
+        watches from the gate and wonders why the dog never moves from the warm patch of \
+        sunlight next to the old barn at the edge of the field";
+
+    fn build_html(token: &str) -> String {
+        format!(
+            r#"<html><head><script>var csrf = "{token}";</script></head>
+            <body><p>{ARTICLE}</p><footer>Generated at {token}</footer></body></html>"#
+        )
+    }
+
+    fn check(detector: &dyn DuplicateDetector, url: &str, body: &str) -> Option<Url> {
+        detector.check(&Url::parse(url).unwrap(), body, &Html::parse_document(body))
+    }
+
+    #[test]
+    fn exact_duplicates() {
+        let detector = ExactDuplicateDetector::default();
+        let first = "https://example.com/a";
+        assert_eq!(check(&detector, first, &build_html("1")), None);
+        assert_eq!(
+            check(&detector, "https://example.com/b", &build_html("2")),
+            None
+        );
+        assert_eq!(
+            check(&detector, "https://example.com/c", &build_html("1")),
+            Some(Url::parse(first).unwrap())
+        );
+    }
+
+    #[test]
+    fn near_duplicates() {
+        let detector = NearDuplicateDetector::new(0.9);
+        let first = "https://example.com/a";
+        assert_eq!(check(&detector, first, &build_html("1700000000")), None);
+        assert_eq!(
+            check(
+                &detector,
+                "https://example.com/b",
+                &build_html("1700000999")
+            ),
+            Some(Url::parse(first).unwrap())
+        );
+
+        let other =
+            "<html><body><p>An entirely different page about something else</p></body></html>";
+        assert_eq!(check(&detector, "https://example.com/c", other), None);
+    }
+
+    #[test]
+    fn match_within_distance() {
+        let first = Url::parse("https://example.com/a").unwrap();
+        let other = Url::parse("https://example.com/b").unwrap();
+        // With a threshold of 0.9, up to 6 bits can differ. One bit in each of the 7 bands:
+        let bits = [0, 10, 19, 28, 37, 46, 55].map(|bit| 1u64 << bit);
+        let detector = NearDuplicateDetector::new(0.9);
+        assert_eq!(detector.insert(0, &first), None);
+        assert_eq!(
+            detector.insert(bits[..6].iter().sum(), &other),
+            Some(first.clone())
+        );
+        assert_eq!(detector.insert(bits.iter().sum(), &other), None);
+
+        // Only identical fingerprints match with a threshold of 1.0, and any do with 0.0.
+        let exact = NearDuplicateDetector::new(1.0);
+        assert_eq!(exact.insert(0, &first), None);
+        assert_eq!(exact.insert(1, &other), None);
+        assert_eq!(exact.insert(1, &first), Some(other.clone()));
+        let any = NearDuplicateDetector::new(0.0);
+        assert_eq!(any.insert(0, &first), None);
+        assert_eq!(any.insert(u64::MAX, &other), Some(first));
+    }
+
+    #[test]
+    fn restore_fingerprints() {
+        let detector = NearDuplicateDetector::new(0.9);
+        let first = "https://example.com/a";
+        check(&detector, first, &build_html("1700000000"));
+        check(
+            &detector,
+            "https://example.com/b",
+            &build_html("1700000999"),
+        );
+        assert_eq!(detector.fingerprints().len(), 1);
+
+        // A detector restored from the fingerprints matches the same pages.
+        let restored = NearDuplicateDetector::new(0.9);
+        for (fingerprint, url) in detector.fingerprints() {
+            restored.insert(fingerprint, &url);
+        }
+        assert_eq!(
+            check(
+                &restored,
+                "https://example.com/c",
+                &build_html("1700000555")
+            ),
+            Some(Url::parse(first).unwrap())
+        );
+    }
+
+    #[test]
+    fn visible_text_only() {
+        let words = NearDuplicateDetector::visible_words(&Html::parse_document(&build_html("1")));
+        assert!(words.contains(&"fox".to_string()));
+        assert!(!words.contains(&"csrf".to_string()));
+    }
+}
@@ -0,0 +1,165 @@
+use crate::{
+    results::{CrawlResults, PageResult},
+    types::LinkKind,
+};
+use serde::Serialize;
+use std::{io, path::Path};
+use url::Url;
+
+/// Writes the link graph of a crawl as delimited text, for spreadsheets and graph tools. Edges
+/// are written one row per link, and nodes one row per visited page, both sorted by URL.
+pub struct EdgeList {
+    delimiter: u8,
+}
+
+/// A link from one page to another.
+#[derive(Serialize)]
+struct Edge<'a> {
+    source: &'a Url,
+    target: &'a Url,
+    kind: LinkKind,
+    text: Option<&'a str>,
+    nofollow: bool,
+}
+
+/// A visited page.
+#[derive(Serialize)]
+struct Node<'a> {
+    url: &'a Url,
+    status: Option<u16>,
+    depth: usize,
+}
+
+impl EdgeList {
+    pub fn new(delimiter: u8) -> Self {
+        EdgeList { delimiter }
+    }
+
+    /// Tab separated values for a `.tsv` file, otherwise comma separated values.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("tsv") => EdgeList::new(b'\t'),
+            _ => EdgeList::new(b','),
+        }
+    }
+
+    /// Write a header row, then a row for every link found on the visited pages.
+    pub fn write_edges(&self, wrt: impl io::Write, results: &CrawlResults) -> csv::Result<()> {
+        let mut writer = self.writer(wrt);
+        for page in Self::sorted_pages(results) {
+            let mut links: Vec<_> = page.links.iter().collect();
+            links.sort();
+            for link in links {
+                writer.serialize(Edge {
+                    source: &page.url,
+                    target: &link.url,
+                    kind: link.kind,
+                    text: link.text.as_deref(),
+                    nofollow: link.nofollow,
+                })?;
+            }
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write a header row, then a row for every visited page.
+    pub fn write_nodes(&self, wrt: impl io::Write, results: &CrawlResults) -> csv::Result<()> {
+        let mut writer = self.writer(wrt);
+        for page in Self::sorted_pages(results) {
+            writer.serialize(Node {
+                url: &page.url,
+                status: page.status.map(|status| status.as_u16()),
+                depth: page.depth,
+            })?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Write the edges to a file, using the delimiter for its extension.
+    pub fn save_edges(path: &Path, results: &CrawlResults) -> csv::Result<()> {
+        EdgeList::for_path(path).write_edges(std::fs::File::create(path)?, results)
+    }
+
+    /// Write the nodes to a file, using the delimiter for its extension.
+    pub fn save_nodes(path: &Path, results: &CrawlResults) -> csv::Result<()> {
+        EdgeList::for_path(path).write_nodes(std::fs::File::create(path)?, results)
+    }
+
+    fn writer<W: io::Write>(&self, wrt: W) -> csv::Writer<W> {
+        csv::WriterBuilder::new()
+            .delimiter(self.delimiter)
+            .from_writer(wrt)
+    }
+
+    fn sorted_pages(results: &CrawlResults) -> Vec<PageResult> {
+        let mut pages = results.pages();
+        pages.sort_by(|a, b| a.url.cmp(&b.url));
+        pages
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EdgeList;
+    use crate::{
+        results::{CrawlResults, PageResult},
+        types::{Link, LinkKind},
+    };
+    use reqwest::StatusCode;
+    use std::path::Path;
+    use url::Url;
+
+    fn build_results() -> CrawlResults {
+        let url = Url::parse("https://example.com/").unwrap();
+        let mut page = PageResult::new(url.clone(), url.clone(), StatusCode::OK);
+        page.links.insert(
+            Link::new(url.join("about").unwrap(), LinkKind::Navigation)
+                .with_text(Some("About, us".to_string())),
+        );
+        page.links
+            .insert(Link::new(url.join("logo.png").unwrap(), LinkKind::Asset).with_nofollow(true));
+        page.links.insert(Link::new(
+            url.join("logo.png").unwrap(),
+            LinkKind::Navigation,
+        ));
+        let about = url.join("about").unwrap();
+        let mut about = PageResult::new(about.clone(), about, StatusCode::NOT_FOUND);
+        about.depth = 1;
+
+        let results = CrawlResults::new();
+        results.insert(page);
+        results.insert(about);
+        results
+    }
+
+    #[test]
+    fn write_edges() {
+        let mut output = Vec::new();
+        EdgeList::new(b',')
+            .write_edges(&mut output, &build_results())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "source,target,kind,text,nofollow\n\
+             https://example.com/,https://example.com/about,navigation,\"About, us\",false\n\
+             https://example.com/,https://example.com/logo.png,navigation,,false\n\
+             https://example.com/,https://example.com/logo.png,asset,,true\n"
+        );
+    }
+
+    #[test]
+    fn write_nodes_tsv() {
+        let mut output = Vec::new();
+        EdgeList::for_path(Path::new("nodes.tsv"))
+            .write_nodes(&mut output, &build_results())
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "url\tstatus\tdepth\n\
+             https://example.com/\t200\t0\n\
+             https://example.com/about\t404\t1\n"
+        );
+    }
+}
@@ -1,37 +1,381 @@
-use reqwest::{Client, StatusCode};
+use crate::{
+    config::{host_entry, sends_credentials, ClientConfig, Credentials, HostConfig, Scope},
+    cookie_jar::CookieJar,
+    html_parser::Form,
+    rate_limiter::RateLimiter,
+    types::{CrawlerError, RobotsDirectives},
+};
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    redirect, Certificate, Client, Method, NoProxy, Proxy, RequestBuilder, Response, StatusCode,
+};
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::Path,
+    time::{Duration, Instant},
+};
 use url::Url;
 
-#[derive(Default)]
+/// The most redirects followed from a URL, as in `reqwest`'s default policy.
+const MAX_REDIRECTS: usize = 10;
+
 pub struct HtmlDownloader {
     client: Client,
+    user_agent: String,
+    /// Headers sent with every request in scope.
+    headers: BTreeMap<String, String>,
+    /// The scope of the crawl and the seed URL's host. The headers above aren't sent to hosts
+    /// outside of it, such as the targets of external links.
+    scope: Option<(Scope, String)>,
+    /// Headers and credentials for particular hosts.
+    hosts: BTreeMap<String, HostConfig>,
+    rate_limiter: RateLimiter,
+    cookies: CookieJar,
 }
 
+impl Default for HtmlDownloader {
+    fn default() -> Self {
+        HtmlDownloader::new(&ClientConfig::default()).unwrap()
+    }
+}
+
+#[derive(Debug)]
 pub struct HtmlPage {
+    /// The URL the content was served from, after following any redirects.
+    pub url: Url,
     pub status: StatusCode,
     pub body: String,
+    /// Directives from the `X-Robots-Tag` response header.
+    pub robots: RobotsDirectives,
+    pub validators: Validators,
+    /// The `Content-Type` response header.
+    pub content_type: Option<String>,
+    /// The time taken to receive the whole response.
+    pub elapsed: Duration,
+}
+
+/// The `ETag` and `Last-Modified` values of a response, which can be sent with a later request
+/// for the same URL so that the server only returns the content if it has changed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
 }
 
 impl HtmlDownloader {
+    /// Create a downloader which makes requests as configured.
+    pub fn new(config: &ClientConfig) -> Result<Self, CrawlerError> {
+        Self::with_env(config, |name| env::var(name).ok())
+    }
+
+    /// Create a downloader which reads proxy settings from the given environment.
+    fn with_env(
+        config: &ClientConfig,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, CrawlerError> {
+        let client_error = |error: reqwest::Error| CrawlerError::ClientError(error.to_string());
+        let cookies = CookieJar::default();
+        let mut builder = Client::builder()
+            .cookie_provider(cookies.provider())
+            .redirect(Self::redirect_policy());
+        if let Some(timeout) = config.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(timeout) = config.connect_timeout {
+            builder = builder.connect_timeout(timeout);
+        }
+        if let Some(proxies) = Self::proxies(config, env).map_err(client_error)? {
+            builder = builder.no_proxy();
+            for proxy in proxies {
+                builder = builder.proxy(proxy);
+            }
+        }
+        if let Some(path) = &config.ca_bundle {
+            for certificate in Self::read_certificates(path)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+        Ok(HtmlDownloader {
+            client: builder.build().map_err(client_error)?,
+            user_agent: config.user_agent.clone(),
+            headers: config.headers.clone(),
+            scope: None,
+            hosts: BTreeMap::new(),
+            rate_limiter: RateLimiter::new(config.rate_limit),
+            cookies,
+        })
+    }
+
+    /// The proxies to send requests through, none of which are used for the `no_proxy` hosts.
+    /// Without `ClientConfig::proxy` they are read from the environment as `reqwest` would,
+    /// with the hosts in `NO_PROXY` skipped too. `None` leaves `reqwest` to read the
+    /// environment itself, when there are no hosts to skip.
+    fn proxies(
+        config: &ClientConfig,
+        env: impl Fn(&str) -> Option<String>,
+    ) -> Result<Option<Vec<Proxy>>, reqwest::Error> {
+        let var = |name: &str| {
+            env(name)
+                .or_else(|| env(&name.to_lowercase()))
+                .filter(|value| !value.is_empty())
+        };
+        let mut no_proxy = config.no_proxy.clone();
+        let proxies = match &config.proxy {
+            Some(proxy) => vec![Proxy::all(proxy.as_str())?],
+            None if no_proxy.is_empty() => return Ok(None),
+            None => {
+                no_proxy.extend(var("NO_PROXY"));
+                let mut proxies = Vec::new();
+                if let Some(proxy) = var("HTTP_PROXY") {
+                    proxies.push(Proxy::http(proxy)?);
+                }
+                if let Some(proxy) = var("HTTPS_PROXY") {
+                    proxies.push(Proxy::https(proxy)?);
+                }
+                if let Some(proxy) = var("ALL_PROXY") {
+                    proxies.push(Proxy::all(proxy)?);
+                }
+                proxies
+            }
+        };
+        let no_proxy = NoProxy::from_string(&no_proxy.join(","));
+        Ok(Some(
+            proxies
+                .into_iter()
+                .map(|proxy| proxy.no_proxy(no_proxy.clone()))
+                .collect(),
+        ))
+    }
+
+    /// Follow redirects within an origin, but stop at one to another origin, so that it can be
+    /// followed with a new request carrying only the headers configured for that host.
+    fn redirect_policy() -> redirect::Policy {
+        redirect::Policy::custom(|attempt| {
+            let cross_origin = attempt
+                .previous()
+                .last()
+                .is_some_and(|previous| previous.origin() != attempt.url().origin());
+            if attempt.previous().len() > MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else if cross_origin {
+                attempt.stop()
+            } else {
+                attempt.follow()
+            }
+        })
+    }
+
+    /// Read the certificates in a PEM file, failing if there are none.
+    fn read_certificates(path: &Path) -> Result<Vec<Certificate>, CrawlerError> {
+        let error = || CrawlerError::CaBundleError {
+            path: path.to_path_buf(),
+        };
+        let pem = fs::read(path).map_err(|_| error())?;
+        match Certificate::from_pem_bundle(&pem) {
+            Ok(certificates) if !certificates.is_empty() => Ok(certificates),
+            _ => Err(error()),
+        }
+    }
+
+    /// The cookies sent with requests, including those set by servers.
+    pub fn cookies(&self) -> &CookieJar {
+        &self.cookies
+    }
+
+    /// Send the headers and credentials of each host with requests to it, and limit the rate
+    /// of requests to it if configured.
+    pub fn with_hosts(mut self, hosts: &BTreeMap<String, HostConfig>) -> Self {
+        for (host, config) in hosts {
+            if let Some(rate) = config.rate_limit {
+                self.rate_limiter = self.rate_limiter.with_host(host, rate);
+            }
+        }
+        self.hosts = hosts.clone();
+        self
+    }
+
+    /// Only send the headers configured for every request to hosts in the scope of a crawl
+    /// from the seed URL. They are sent to every host by default.
+    pub fn with_scope(mut self, scope: Scope, seed: &Url) -> Self {
+        self.scope = Some((scope, seed.host_str().unwrap_or("").to_string()));
+        self
+    }
+
+    /// The configured headers to send to the URL, with those for its host taking precedence.
+    /// Invalid headers are left out.
+    fn headers_for(&self, url: &Url) -> HeaderMap {
+        let in_scope = self.scope.as_ref().is_none_or(|(scope, subdomain)| {
+            scope.includes_host(subdomain, url.host_str().unwrap_or(""))
+        });
+        let global = self.headers.iter().filter(|_| in_scope);
+        let host = host_entry(&self.hosts, url);
+        let mut headers = HeaderMap::new();
+        for (name, value) in global.chain(host.into_iter().flat_map(|host| &host.headers)) {
+            if let (Ok(name), Ok(value)) =
+                (HeaderName::try_from(name), HeaderValue::try_from(value))
+            {
+                headers.insert(name, value);
+            }
+        }
+        headers
+    }
+
     /// Fetch the HTML content of the URL.
     pub async fn fetch(&self, url: Url) -> Result<HtmlPage, reqwest::Error> {
-        let response = self
+        self.fetch_conditional(url, &Validators::default()).await
+    }
+
+    /// Fetch the HTML content of the URL if it has changed since the validators were issued.
+    /// Otherwise the server responds with `304 Not Modified` and an empty body.
+    pub async fn fetch_conditional(
+        &self,
+        url: Url,
+        validators: &Validators,
+    ) -> Result<HtmlPage, reqwest::Error> {
+        let mut request = self.request(Method::GET, &url);
+        if let Some(etag) = &validators.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        self.send(&url, request).await
+    }
+
+    /// Check whether the URL can be fetched, without downloading its content. The URL is
+    /// requested with `HEAD`, or with `GET` when the server doesn't support `HEAD`.
+    pub async fn check(&self, url: Url) -> Result<HtmlPage, reqwest::Error> {
+        let page = self.send(&url, self.request(Method::HEAD, &url)).await?;
+        match page.status {
+            StatusCode::METHOD_NOT_ALLOWED | StatusCode::NOT_IMPLEMENTED => self.fetch(url).await,
+            _ => Ok(page),
+        }
+    }
+
+    /// Submit the form with the values of its fields, returning the page responded with.
+    pub async fn submit(&self, form: &Form) -> Result<HtmlPage, reqwest::Error> {
+        let request = self.request(form.method.clone(), &form.action);
+        let request = if form.method == Method::GET {
+            request.query(&form.fields)
+        } else {
+            request.form(&form.fields)
+        };
+        self.send(&form.action, request).await
+    }
+
+    /// Where the response redirects a request made with the method, if it was left for the
+    /// caller to follow.
+    fn redirect_target(method: &Method, response: &Response) -> Option<Url> {
+        let follow = match response.status() {
+            StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND | StatusCode::SEE_OTHER => true,
+            // The request must be repeated as it was, which is only possible without a body.
+            StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => {
+                matches!(*method, Method::GET | Method::HEAD)
+            }
+            _ => false,
+        };
+        if !follow {
+            return None;
+        }
+        let location = response.headers().get(header::LOCATION)?.to_str().ok()?;
+        response.url().join(location).ok()
+    }
+
+    /// Start a request with the user agent, and the headers and credentials configured for the
+    /// URL's host.
+    fn request(&self, method: Method, url: &Url) -> RequestBuilder {
+        let request = self
             .client
-            .get(url.clone())
-            .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64)")
-            .send()
-            .await?;
+            .request(method, url.clone())
+            .header(header::USER_AGENT, &self.user_agent)
+            .headers(self.headers_for(url));
+        let credentials = host_entry(&self.hosts, url)
+            .and_then(|host| host.credentials.as_ref())
+            .filter(|_| sends_credentials(&self.hosts, url));
+        match credentials {
+            Some(Credentials::Basic { username, password }) => {
+                request.basic_auth(username, password.as_ref())
+            }
+            Some(Credentials::Bearer(token)) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Send the request once the rate limit for the URL's host allows, and read the response.
+    /// A redirect to another origin is followed with a new request to that host.
+    async fn send(&self, url: &Url, request: RequestBuilder) -> Result<HtmlPage, reqwest::Error> {
+        self.rate_limiter.wait(url).await;
+        let started = Instant::now();
+        let mut request = request.build()?;
+        let mut redirects = 0;
+        let response = loop {
+            let method = request.method().clone();
+            let response = self.client.execute(request).await?;
+            match Self::redirect_target(&method, &response) {
+                Some(target) if redirects < MAX_REDIRECTS => {
+                    redirects += 1;
+                    self.rate_limiter.wait(&target).await;
+                    let method = if method == Method::HEAD {
+                        Method::HEAD
+                    } else {
+                        Method::GET
+                    };
+                    request = self.request(method, &target).build()?;
+                }
+                _ => break response,
+            }
+        };
+        let robots = response
+            .headers()
+            .get_all("X-Robots-Tag")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .map(RobotsDirectives::parse)
+            .fold(RobotsDirectives::default(), RobotsDirectives::merge);
+        let header_value = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        let validators = Validators {
+            etag: header_value(header::ETAG),
+            last_modified: header_value(header::LAST_MODIFIED),
+        };
+        let content_type = header_value(header::CONTENT_TYPE);
+        let url = response.url().clone();
+        let status = response.status();
+        let body = response.text().await?;
         Ok(HtmlPage {
-            status: response.status(),
-            body: response.text().await?,
+            url,
+            status,
+            body,
+            robots,
+            validators,
+            content_type,
+            elapsed: started.elapsed(),
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::HtmlDownloader;
+    use super::{HtmlDownloader, Validators};
+    use crate::{
+        config::{ClientConfig, Credentials, HostConfig},
+        types::CrawlerError,
+    };
+    use reqwest::Method;
+    use std::{collections::BTreeMap, net::SocketAddr};
+    use tokio::{
+        io::{self, AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        task::JoinHandle,
+    };
     use url::Url;
-    use wiremock::matchers::{method, path};
+    use wiremock::matchers::{header, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     async fn build_mock_server(endpoint: &str, response_body: &str) -> MockServer {
@@ -56,10 +400,274 @@ mod tests {
         let downloader = HtmlDownloader::default();
         let page = downloader.fetch(Url::parse(&url).unwrap()).await.unwrap();
 
+        assert_eq!(page.url.as_str(), url);
         assert_eq!(page.status.as_u16(), 200);
         assert_eq!(page.body, response_body);
     }
 
+    #[tokio::test]
+    async fn fetch_with_user_agent() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/hello"))
+            .and(header("User-Agent", "TestBot/1.0"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        let config = ClientConfig {
+            user_agent: "TestBot/1.0".to_string(),
+            ..Default::default()
+        };
+        let downloader = HtmlDownloader::new(&config).unwrap();
+        let url = Url::parse(&format!("{}/hello", mock_server.uri())).unwrap();
+        let page = downloader.fetch(url).await.unwrap();
+
+        assert_eq!(page.status.as_u16(), 200);
+    }
+
+    #[tokio::test]
+    async fn fetch_with_host_headers() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/hello"))
+            .and(header("X-Preview-Key", "abc"))
+            .and(header("Authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        let url = Url::parse(&format!("{}/hello", mock_server.uri())).unwrap();
+        let host = HostConfig {
+            headers: BTreeMap::from([("X-Preview-Key".to_string(), "abc".to_string())]),
+            credentials: Some(Credentials::Bearer("secret".to_string())),
+            ..Default::default()
+        };
+        let hosts = BTreeMap::from([(url.origin().ascii_serialization(), host)]);
+
+        // Requests to other hosts are sent without them.
+        let page = HtmlDownloader::default().fetch(url.clone()).await.unwrap();
+        assert_eq!(page.status.as_u16(), 404);
+        let downloader = HtmlDownloader::default().with_hosts(&hosts);
+        let page = downloader.fetch(url).await.unwrap();
+        assert_eq!(page.status.as_u16(), 200);
+    }
+
+    #[tokio::test]
+    async fn fetch_with_headers_and_cookies() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/login"))
+            .respond_with(
+                ResponseTemplate::new(200).append_header("Set-Cookie", "session=abc; Path=/"),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/private"))
+            .and(header("Cookie", "session=abc"))
+            .and(header("X-Preview-Key", "key"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+        let config = ClientConfig {
+            headers: BTreeMap::from([("X-Preview-Key".to_string(), "key".to_string())]),
+            ..Default::default()
+        };
+        let downloader = HtmlDownloader::new(&config).unwrap();
+        let url = |path| Url::parse(&format!("{}{}", mock_server.uri(), path)).unwrap();
+
+        let page = downloader.fetch(url("/private")).await.unwrap();
+        assert_eq!(page.status.as_u16(), 404);
+        // The cookie set by the server is sent with later requests.
+        downloader.fetch(url("/login")).await.unwrap();
+        let page = downloader.fetch(url("/private")).await.unwrap();
+        assert_eq!(page.status.as_u16(), 200);
+        assert_eq!(
+            downloader.cookies().cookies(&url("/")),
+            vec![("session".to_string(), "abc".to_string())]
+        );
+    }
+
+    #[tokio::test]
+    async fn send_credentials_to_matching_origin_only() {
+        let site = MockServer::start().await;
+        let external = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/private"))
+            .and(header("Authorization", "Basic Y2k6czNjcmV0"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&site)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/moved"))
+            .respond_with(
+                ResponseTemplate::new(301)
+                    .append_header("Location", format!("{}/landing", external.uri())),
+            )
+            .mount(&site)
+            .await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&external)
+            .await;
+
+        // The external server runs on the same host, on another port.
+        let url =
+            |server: &MockServer, path| Url::parse(&format!("{}{}", server.uri(), path)).unwrap();
+        let key = url(&site, "/").origin().ascii_serialization();
+        let host = HostConfig {
+            credentials: Some(Credentials::Basic {
+                username: "ci".to_string(),
+                password: Some("s3cret".to_string()),
+            }),
+            ..Default::default()
+        };
+        let downloader = HtmlDownloader::default().with_hosts(&BTreeMap::from([(key, host)]));
+
+        let page = downloader.fetch(url(&site, "/private")).await.unwrap();
+        assert_eq!(page.status.as_u16(), 200);
+        downloader.fetch(url(&external, "/link")).await.unwrap();
+        let page = downloader.fetch(url(&site, "/moved")).await.unwrap();
+        assert_eq!(page.url, url(&external, "/landing"));
+
+        let requests = external.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests
+            .iter()
+            .all(|request| !request.headers.contains_key("Authorization")));
+    }
+
+    #[test]
+    fn send_credentials_over_https_only() {
+        let host = HostConfig {
+            credentials: Some(Credentials::Bearer("secret".to_string())),
+            ..Default::default()
+        };
+        let authorization = |hosts: &BTreeMap<String, HostConfig>, url: &str| {
+            let downloader = HtmlDownloader::default().with_hosts(hosts);
+            let request = downloader
+                .request(Method::GET, &Url::parse(url).unwrap())
+                .build()
+                .unwrap();
+            request.headers().contains_key("Authorization")
+        };
+
+        let hosts = BTreeMap::from([("docs.example.com".to_string(), host.clone())]);
+        assert!(authorization(&hosts, "https://docs.example.com/guide"));
+        assert!(!authorization(&hosts, "http://docs.example.com/guide"));
+
+        // Unless the host is configured by its http origin.
+        let hosts = BTreeMap::from([("http://docs.example.com".to_string(), host)]);
+        assert!(authorization(&hosts, "http://docs.example.com/guide"));
+        assert!(!authorization(&hosts, "http://docs.example.com:8080/guide"));
+    }
+
+    #[tokio::test]
+    async fn send_host_headers_per_redirect() {
+        let site = MockServer::start().await;
+        let external = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/moved"))
+            .and(header("X-Preview-Key", "site"))
+            .respond_with(
+                ResponseTemplate::new(301)
+                    .append_header("Location", format!("{}/landing", external.uri())),
+            )
+            .mount(&site)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/landing"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&external)
+            .await;
+
+        let url =
+            |server: &MockServer, path| Url::parse(&format!("{}{}", server.uri(), path)).unwrap();
+        let key = |server: &MockServer| {
+            let url = url(server, "/");
+            format!("{}:{}", url.host_str().unwrap(), url.port().unwrap())
+        };
+        let host = |key: &str| HostConfig {
+            headers: BTreeMap::from([("X-Preview-Key".to_string(), key.to_string())]),
+            ..Default::default()
+        };
+        let downloader =
+            HtmlDownloader::default().with_hosts(&BTreeMap::from([(key(&site), host("site"))]));
+
+        let page = downloader.fetch(url(&site, "/moved")).await.unwrap();
+        assert_eq!(page.status.as_u16(), 200);
+        assert_eq!(page.url, url(&external, "/landing"));
+        let requests = external.received_requests().await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(!requests[0].headers.contains_key("X-Preview-Key"));
+
+        // The external host is sent its own headers instead.
+        let downloader = HtmlDownloader::default().with_hosts(&BTreeMap::from([
+            (key(&site), host("site")),
+            (key(&external), host("external")),
+        ]));
+        downloader.fetch(url(&site, "/moved")).await.unwrap();
+        let requests = external.received_requests().await.unwrap();
+        assert_eq!(requests[1].headers["X-Preview-Key"], "external");
+    }
+
+    #[tokio::test]
+    async fn fetch_robots_header() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/hello"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .append_header("X-Robots-Tag", "noarchive, nofollow")
+                    .append_header("X-Robots-Tag", "otherbot: noindex"),
+            )
+            .mount(&mock_server)
+            .await;
+        let url = format!("{}/hello", mock_server.uri());
+        let downloader = HtmlDownloader::default();
+        let page = downloader.fetch(Url::parse(&url).unwrap()).await.unwrap();
+
+        assert!(page.robots.nofollow);
+        assert!(!page.robots.noindex);
+    }
+
+    #[tokio::test]
+    async fn fetch_conditional() {
+        let mock_server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/hello"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/hello"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .append_header("ETag", "\"v1\"")
+                    .append_header("Last-Modified", "Wed, 21 Oct 2015 07:28:00 GMT"),
+            )
+            .mount(&mock_server)
+            .await;
+        let url = Url::parse(&format!("{}/hello", mock_server.uri())).unwrap();
+        let downloader = HtmlDownloader::default();
+
+        let page = downloader.fetch(url.clone()).await.unwrap();
+        assert_eq!(page.status.as_u16(), 200);
+        assert_eq!(
+            page.validators,
+            Validators {
+                etag: Some("\"v1\"".to_string()),
+                last_modified: Some("Wed, 21 Oct 2015 07:28:00 GMT".to_string()),
+            }
+        );
+
+        let page = downloader
+            .fetch_conditional(url, &page.validators)
+            .await
+            .unwrap();
+        assert_eq!(page.status.as_u16(), 304);
+    }
+
     #[tokio::test]
     async fn fetch_not_found() {
         let response_body = "Body of mocked response";
@@ -71,4 +679,124 @@ mod tests {
         assert_eq!(page.status.as_u16(), 404);
         assert_eq!(page.body, "");
     }
+
+    #[tokio::test]
+    async fn fetch_through_proxy() {
+        let site = build_mock_server("/hello", "direct").await;
+        // A plain HTTP proxy is sent the request as it would be sent to the site, so a mock
+        // server can stand in for one.
+        let proxy = build_mock_server("/hello", "proxied").await;
+        let url = Url::parse(&format!("{}/hello", site.uri())).unwrap();
+        let mut config = ClientConfig {
+            proxy: Some(Url::parse(&proxy.uri()).unwrap()),
+            ..Default::default()
+        };
+
+        let page = HtmlDownloader::new(&config)
+            .unwrap()
+            .fetch(url.clone())
+            .await
+            .unwrap();
+        assert_eq!(page.body, "proxied");
+        assert_eq!(page.url, url);
+
+        config.no_proxy = vec!["example.com".to_string(), "127.0.0.1".to_string()];
+        let page = HtmlDownloader::new(&config)
+            .unwrap()
+            .fetch(url)
+            .await
+            .unwrap();
+        assert_eq!(page.body, "direct");
+    }
+
+    #[tokio::test]
+    async fn skip_proxy_from_environment() {
+        let site = build_mock_server("/hello", "direct").await;
+        let proxy = build_mock_server("/hello", "proxied").await;
+        let url = Url::parse(&format!("{}/hello", site.uri())).unwrap();
+        let proxy_uri = proxy.uri();
+        let env = |name: &str| (name == "http_proxy").then(|| proxy_uri.clone());
+
+        // The proxy from the environment is used for hosts other than the no_proxy hosts.
+        let mut config = ClientConfig {
+            no_proxy: vec!["example.com".to_string()],
+            ..Default::default()
+        };
+        let downloader = HtmlDownloader::with_env(&config, env).unwrap();
+        let page = downloader.fetch(url.clone()).await.unwrap();
+        assert_eq!(page.body, "proxied");
+
+        config.no_proxy = vec!["127.0.0.1".to_string()];
+        let downloader = HtmlDownloader::with_env(&config, env).unwrap();
+        let page = downloader.fetch(url).await.unwrap();
+        assert_eq!(page.body, "direct");
+    }
+
+    /// Accept one connection as a SOCKS5 proxy without authentication, and relay it to the
+    /// address requested.
+    async fn socks5_proxy() -> (SocketAddr, JoinHandle<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let relay = tokio::spawn(async move {
+            let (mut client, _) = listener.accept().await.unwrap();
+            let mut greeting = [0; 2];
+            client.read_exact(&mut greeting).await.unwrap();
+            let mut methods = vec![0; greeting[1] as usize];
+            client.read_exact(&mut methods).await.unwrap();
+            client.write_all(&[5, 0]).await.unwrap();
+
+            // Only IPv4 addresses are requested with a socks5 URL.
+            let mut request = [0; 10];
+            client.read_exact(&mut request).await.unwrap();
+            assert_eq!(request[..4], [5, 1, 0, 1]);
+            let ip: [u8; 4] = request[4..8].try_into().unwrap();
+            let port = u16::from_be_bytes([request[8], request[9]]);
+            let mut site = TcpStream::connect(SocketAddr::from((ip, port)))
+                .await
+                .unwrap();
+            client
+                .write_all(&[5, 0, 0, 1, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+            let _ = io::copy_bidirectional(&mut client, &mut site).await;
+        });
+        (addr, relay)
+    }
+
+    #[tokio::test]
+    async fn fetch_through_socks_proxy() {
+        let site = build_mock_server("/hello", "relayed").await;
+        let (proxy, relay) = socks5_proxy().await;
+        let config = ClientConfig {
+            proxy: Some(Url::parse(&format!("socks5://{}", proxy)).unwrap()),
+            ..Default::default()
+        };
+        let downloader = HtmlDownloader::new(&config).unwrap();
+
+        let url = Url::parse(&format!("{}/hello", site.uri())).unwrap();
+        let page = downloader.fetch(url).await.unwrap();
+        assert_eq!(page.body, "relayed");
+        // The relay ends once the downloader closes its connection to the proxy.
+        drop(downloader);
+        relay.await.unwrap();
+    }
+
+    #[test]
+    fn trust_ca_bundle() {
+        let config = ClientConfig {
+            ca_bundle: Some(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/ca.pem").into()),
+            ..Default::default()
+        };
+        assert!(HtmlDownloader::new(&config).is_ok());
+
+        // A file without any certificates is an error rather than silently trusting nothing.
+        let config = ClientConfig {
+            ca_bundle: Some(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml").into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            HtmlDownloader::new(&config),
+            Err(CrawlerError::CaBundleError { .. })
+        ));
+    }
 }
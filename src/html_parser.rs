@@ -1,55 +1,351 @@
-use crate::store::Store;
-use scraper::{Html, Selector};
-use sha2::{Digest, Sha256};
-use std::collections::HashSet;
+use crate::{
+    duplicate::{DuplicateDetector, ExactDuplicateDetector},
+    store::VisitedStore,
+    types::{Link, LinkKind, RobotsDirectives},
+};
+use reqwest::Method;
+use scraper::{ElementRef, Html, Selector};
+use std::collections::{BTreeMap, HashSet};
 use url::Url;
 
-#[derive(Default)]
 pub struct HtmlParser {
-    html_store: Store<String>,
+    detectors: Vec<Box<dyn DuplicateDetector>>,
+}
+
+impl Default for HtmlParser {
+    /// A parser which detects pages whose body exactly matches an earlier page.
+    fn default() -> Self {
+        HtmlParser {
+            detectors: vec![Box::new(ExactDuplicateDetector::default())],
+        }
+    }
+}
+
+/// The information extracted from a page.
+#[derive(Debug, Default)]
+pub struct ParsedPage {
+    pub links: HashSet<Link>,
+    /// Directives from `<meta name="robots">`.
+    pub robots: RobotsDirectives,
+    /// The URL from `<link rel="canonical">`.
+    pub canonical: Option<Url>,
+    /// Alternate versions of the page by language, from `<link rel="alternate" hreflang>`.
+    pub hreflang: BTreeMap<String, Url>,
+    /// The earlier page whose content this page duplicates.
+    pub duplicate_of: Option<Url>,
+}
+
+/// A form on a page, with the values its fields would be submitted with.
+#[derive(Debug, PartialEq)]
+pub struct Form {
+    /// The URL the form is submitted to, resolved against the page's URL.
+    pub action: Url,
+    /// `GET` or `POST`, from the `method` attribute.
+    pub method: Method,
+    /// The name and value of each field in document order, including hidden fields such as CSRF
+    /// tokens. Unchecked boxes and buttons are left out.
+    pub fields: Vec<(String, String)>,
+}
+
+impl Form {
+    /// Set the value of a field, adding it if the form doesn't have one with that name.
+    pub fn set(&mut self, name: &str, value: &str) {
+        match self.fields.iter_mut().find(|(field, _)| field == name) {
+            Some((_, current)) => *current = value.to_string(),
+            None => self.fields.push((name.to_string(), value.to_string())),
+        }
+    }
 }
 
 impl HtmlParser {
-    /// Parse the HTML body and return the links found on that page.
-    pub fn parse(&self, body: String) -> HashSet<Url> {
-        // Check whether this page has been visited before.
-        if self.visited_before(&body) {
-            return HashSet::new();
+    /// Create a parser which detects exact duplicates using the given store of page hashes.
+    pub fn new(html_store: impl VisitedStore<String, Option<Url>> + 'static) -> Self {
+        HtmlParser {
+            detectors: vec![Box::new(ExactDuplicateDetector::new(html_store))],
         }
+    }
 
-        // Page hasn't been seen, extract URLs.
-        Self::extract_urls(body)
+    /// Add a detector, run after the detectors already configured.
+    pub fn with_detector(mut self, detector: impl DuplicateDetector + 'static) -> Self {
+        self.detectors.push(Box::new(detector));
+        self
     }
 
-    /// Calculate hash of the body and check whether it has been seen before.
-    fn visited_before(&self, body: &str) -> bool {
-        let body_hash = Self::calculate_hash(body);
+    /// Parse the HTML body of the URL, served from `final_url` after any redirects, and return
+    /// the links and directives found on that page. Relative links are resolved against the
+    /// page's `<base href>`, or else its final URL.
+    pub fn parse(&self, url: &Url, final_url: &Url, body: String) -> ParsedPage {
+        let html = Html::parse_document(&body);
+
+        // Check whether this page has been visited before. Duplicates are still parsed, so the
+        // page can be reported alongside the page it duplicates.
+        let duplicate_of = self.visited_before(url, &body, &html);
 
-        // Store this page's hash for future comparisons.
-        !self.html_store.insert(body_hash)
+        let base = Self::base_url(final_url, &html);
+        ParsedPage {
+            links: Self::extract_links(&base, &html),
+            robots: Self::extract_robots(&html),
+            canonical: Self::extract_canonical(&base, &html),
+            hreflang: Self::extract_hreflang(&base, &html),
+            duplicate_of,
+        }
     }
 
-    /// Calculate the SHA-256 hash of the body.
-    fn calculate_hash(body: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(body);
-        format!("{:x}", hasher.finalize())
+    /// Record a page which wasn't modified since the previous crawl, by the hash of its body
+    /// from then, and return the URL of the earlier page it duplicates if there is one.
+    pub fn record_unchanged(&self, url: &Url, content_hash: &str) -> Option<Url> {
+        self.detectors
+            .iter()
+            .find_map(|detector| detector.check_hash(url, content_hash))
     }
 
-    /// Extract the links found in the body.
-    fn extract_urls(body: String) -> HashSet<Url> {
-        let html = Html::parse_document(&body);
-        let selector = Selector::parse("a").unwrap();
+    /// Extract the forms matching the CSS selector from the body of the URL. Returns `None` if the
+    /// selector is invalid.
+    pub fn extract_forms(url: &Url, body: &str, selector: &str) -> Option<Vec<Form>> {
+        let selector = Selector::parse(selector).ok()?;
+        let fields = Selector::parse("input[name], textarea[name], select[name]").unwrap();
+        let options = Selector::parse("option").unwrap();
+        let html = Html::parse_document(body);
+        let base = Self::base_url(url, &html);
+
+        let forms = html
+            .select(&selector)
+            .filter(|element| element.value().name() == "form")
+            .map(|form| {
+                let attr = |name| form.value().attr(name).map(str::trim);
+                let action = attr("action")
+                    .filter(|action| !action.is_empty())
+                    .and_then(|action| base.join(action).ok())
+                    .unwrap_or_else(|| url.clone());
+                let method = match attr("method") {
+                    Some(method) if method.eq_ignore_ascii_case("post") => Method::POST,
+                    _ => Method::GET,
+                };
+                let fields = form
+                    .select(&fields)
+                    .filter_map(|field| {
+                        let element = field.value();
+                        let name = element.attr("name")?.to_string();
+                        let value = match element.name() {
+                            "textarea" => field.text().collect(),
+                            "select" => {
+                                let mut choices = field.select(&options);
+                                let selected = choices
+                                    .clone()
+                                    .find(|option| option.value().attr("selected").is_some())
+                                    .or_else(|| choices.next())?;
+                                match selected.value().attr("value") {
+                                    Some(value) => value.to_string(),
+                                    None => selected.text().collect::<String>().trim().to_string(),
+                                }
+                            }
+                            _ => {
+                                let kind = element.attr("type").unwrap_or("text");
+                                let kind = kind.to_ascii_lowercase();
+                                let checkable = kind == "checkbox" || kind == "radio";
+                                if matches!(
+                                    kind.as_str(),
+                                    "submit" | "button" | "image" | "reset" | "file"
+                                ) || (checkable && element.attr("checked").is_none())
+                                {
+                                    return None;
+                                }
+                                let default = if checkable { "on" } else { "" };
+                                element.attr("value").unwrap_or(default).to_string()
+                            }
+                        };
+                        Some((name, value))
+                    })
+                    .collect();
+                Form {
+                    action,
+                    method,
+                    fields,
+                }
+            })
+            .collect();
+        Some(forms)
+    }
+
+    /// Check whether the page's content has been seen before, returning the URL it was seen at.
+    /// Detectors are run in order and the page is recorded by each until one finds a match.
+    fn visited_before(&self, url: &Url, body: &str, html: &Html) -> Option<Url> {
+        self.detectors
+            .iter()
+            .find_map(|detector| detector.check(url, body, html))
+    }
+
+    /// Return the URL relative links are resolved against: the first `<base href>`, itself
+    /// resolved against the page's URL, or else the page's URL.
+    fn base_url(url: &Url, html: &Html) -> Url {
+        let selector = Selector::parse("base[href]").unwrap();
+        html.select(&selector)
+            .filter_map(|element| element.value().attr("href"))
+            .find_map(|href| url.join(href.trim()).ok())
+            .unwrap_or_else(|| url.clone())
+    }
+
+    /// Resolve a link against the base URL, dropping the fragment as it never reaches the
+    /// server.
+    fn resolve(base: &Url, href: &str) -> Option<Url> {
+        let mut url = base.join(href.trim()).ok()?;
+        url.set_fragment(None);
+        Some(url)
+    }
+
+    /// Extract the links found in the body, resolved against the base URL and tagged with the
+    /// kind of element they came from.
+    fn extract_links(base: &Url, html: &Html) -> HashSet<Link> {
+        let mut links = HashSet::new();
+
+        for (selector, attribute, kind) in LINK_SOURCES {
+            let selector = Selector::parse(selector).unwrap();
+            for element in html.select(&selector) {
+                let nofollow = Self::has_rel(&element, "nofollow");
+                for href in Self::attribute_urls(&element, attribute) {
+                    if let Some(url) = Self::resolve(base, href) {
+                        let link = Link::new(url, *kind)
+                            .with_nofollow(nofollow)
+                            .with_text(Self::link_text(&element));
+                        links.insert(link);
+                    }
+                }
+            }
+        }
+
+        // A meta refresh redirects the browser, so treat it as navigation.
+        let selector = Selector::parse("meta[http-equiv]").unwrap();
+        html.select(&selector)
+            .filter(|element| {
+                element
+                    .value()
+                    .attr("http-equiv")
+                    .is_some_and(|value| value.eq_ignore_ascii_case("refresh"))
+            })
+            .filter_map(|element| element.value().attr("content"))
+            .filter_map(Self::refresh_url)
+            .filter_map(|href| Self::resolve(base, href))
+            .for_each(|url| {
+                links.insert(Link::new(url, LinkKind::Navigation));
+            });
+
+        links
+    }
+
+    /// The visible text of a link element with whitespace collapsed, or its `alt` text.
+    fn link_text(element: &ElementRef) -> Option<String> {
+        let text = element.text().flat_map(str::split_whitespace);
+        let text = text.collect::<Vec<_>>().join(" ");
+        if text.is_empty() {
+            element
+                .value()
+                .attr("alt")
+                .map(|alt| alt.trim().to_string())
+        } else {
+            Some(text)
+        }
+    }
+
+    /// Extract the directives from `<meta name="robots">` elements.
+    fn extract_robots(html: &Html) -> RobotsDirectives {
+        let selector = Selector::parse("meta[name][content]").unwrap();
         html.select(&selector)
+            .filter(|element| {
+                element
+                    .value()
+                    .attr("name")
+                    .is_some_and(|name| name.eq_ignore_ascii_case("robots"))
+            })
+            .filter_map(|element| element.value().attr("content"))
+            .map(RobotsDirectives::parse)
+            .fold(RobotsDirectives::default(), RobotsDirectives::merge)
+    }
+
+    /// Extract the URL from the first `<link rel="canonical">` element, resolved against the
+    /// base URL.
+    fn extract_canonical(base: &Url, html: &Html) -> Option<Url> {
+        let selector = Selector::parse("link[rel][href]").unwrap();
+        html.select(&selector)
+            .filter(|element| Self::has_rel(element, "canonical"))
             .filter_map(|element| element.value().attr("href"))
-            .filter_map(|href| Url::parse(href).ok())
+            .find_map(|href| base.join(href.trim()).ok())
+    }
+
+    /// Extract the alternate language versions of the page, keyed by lowercase language code
+    /// and resolved against the base URL.
+    fn extract_hreflang(base: &Url, html: &Html) -> BTreeMap<String, Url> {
+        let selector = Selector::parse("link[rel][hreflang][href]").unwrap();
+        html.select(&selector)
+            .filter(|element| Self::has_rel(element, "alternate"))
+            .filter_map(|element| {
+                let lang = element
+                    .value()
+                    .attr("hreflang")?
+                    .trim()
+                    .to_ascii_lowercase();
+                let alternate = base.join(element.value().attr("href")?.trim()).ok()?;
+                Some((lang, alternate))
+            })
             .collect()
     }
+
+    /// Determine whether the element's `rel` attribute contains the given link type.
+    fn has_rel(element: &ElementRef, link_type: &str) -> bool {
+        element.value().attr("rel").is_some_and(|rel| {
+            rel.split_whitespace()
+                .any(|value| value.eq_ignore_ascii_case(link_type))
+        })
+    }
+
+    /// Return the URLs held by an attribute. A `srcset` holds a comma separated list of
+    /// candidates, each a URL optionally followed by a descriptor such as `2x`.
+    fn attribute_urls<'a>(element: &ElementRef<'a>, attribute: &str) -> Vec<&'a str> {
+        let Some(value) = element.value().attr(attribute) else {
+            return Vec::new();
+        };
+        if attribute == "srcset" {
+            value
+                .split(',')
+                .filter_map(|candidate| candidate.split_whitespace().next())
+                .collect()
+        } else {
+            vec![value.trim()]
+        }
+    }
+
+    /// Extract the URL from a meta refresh `content` value, e.g. `5; url=https://example.com`.
+    fn refresh_url(content: &str) -> Option<&str> {
+        let (_, target) = content.split_once(';')?;
+        let (key, url) = target.split_once('=')?;
+        if !key.trim().eq_ignore_ascii_case("url") {
+            return None;
+        }
+        Some(url.trim().trim_matches(|c| c == '\'' || c == '"'))
+    }
 }
 
+/// Elements which reference other URLs, the attribute holding the URL and the kind of link.
+const LINK_SOURCES: &[(&str, &str, LinkKind)] = &[
+    ("a", "href", LinkKind::Navigation),
+    ("area", "href", LinkKind::Navigation),
+    ("link", "href", LinkKind::Asset),
+    ("img", "src", LinkKind::Asset),
+    ("img", "srcset", LinkKind::Asset),
+    ("script", "src", LinkKind::Asset),
+    ("source", "src", LinkKind::Asset),
+    ("source", "srcset", LinkKind::Asset),
+    ("iframe", "src", LinkKind::Embed),
+    ("frame", "src", LinkKind::Embed),
+    ("form", "action", LinkKind::Form),
+];
+
 #[cfg(test)]
 mod tests {
-    use super::HtmlParser;
+    use super::{Form, HtmlParser};
+    use crate::duplicate::{ExactDuplicateDetector, NearDuplicateDetector};
+    use crate::types::{Link, LinkKind};
+    use reqwest::Method;
+    use scraper::Html;
     use url::Url;
 
     fn build_html(seed_link: &String) -> String {
@@ -69,25 +365,274 @@ mod tests {
         .to_string()
     }
 
+    fn visited_before(html_parser: &HtmlParser, url: &str, body: &str) -> Option<Url> {
+        let url = Url::parse(url).unwrap();
+        html_parser.visited_before(&url, body, &Html::parse_document(body))
+    }
+
     #[tokio::test]
     async fn visit_new_page() {
         let html_parser = HtmlParser::default();
-        assert!(!html_parser.visited_before(&build_html(&"https://example.com/kolo".to_string())));
-        assert!(!html_parser.visited_before(&build_html(&"https://example.com/yaya".to_string())));
+        let kolo = build_html(&"https://example.com/kolo".to_string());
+        let yaya = build_html(&"https://example.com/yaya".to_string());
+        assert!(visited_before(&html_parser, "https://example.com/a", &kolo).is_none());
+        assert!(visited_before(&html_parser, "https://example.com/b", &yaya).is_none());
     }
 
     #[tokio::test]
     async fn visit_same_page() {
         let html_parser = HtmlParser::default();
-        assert!(!html_parser.visited_before(&build_html(&"https://example.com/kolo".to_string())));
-        assert!(html_parser.visited_before(&build_html(&"https://example.com/kolo".to_string())));
+        let kolo = build_html(&"https://example.com/kolo".to_string());
+        assert!(visited_before(&html_parser, "https://example.com/a", &kolo).is_none());
+        assert_eq!(
+            visited_before(&html_parser, "https://example.com/b", &kolo),
+            Some(Url::parse("https://example.com/a").unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn visit_unchanged_page() {
+        let html_parser = HtmlParser::default();
+        let kolo = build_html(&"https://example.com/kolo".to_string());
+        let hash = ExactDuplicateDetector::calculate_hash(&kolo);
+        let a = Url::parse("https://example.com/a").unwrap();
+        assert!(html_parser.record_unchanged(&a, &hash).is_none());
+        assert_eq!(
+            visited_before(&html_parser, "https://example.com/b", &kolo),
+            Some(a)
+        );
+    }
+
+    #[tokio::test]
+    async fn visit_similar_page() {
+        let html_parser = HtmlParser::default().with_detector(NearDuplicateDetector::new(0.9));
+        let kolo = build_html(&"https://example.com/kolo".to_string());
+        let yaya = build_html(&"https://example.com/yaya".to_string());
+        assert!(visited_before(&html_parser, "https://example.com/a", &kolo).is_none());
+        assert_eq!(
+            visited_before(&html_parser, "https://example.com/b", &yaya),
+            Some(Url::parse("https://example.com/a").unwrap())
+        );
     }
 
     #[tokio::test]
     async fn extract_urls() {
         let seed_link = "https://example.com/toure".to_string();
-        let urls = HtmlParser::extract_urls(build_html(&seed_link));
-        assert!(urls.len() == 2);
-        assert!(urls.contains(&Url::parse(&seed_link).unwrap()));
+        let base = Url::parse("https://example.com/").unwrap();
+        let links =
+            HtmlParser::extract_links(&base, &Html::parse_document(&build_html(&seed_link)));
+        assert!(links.len() == 3);
+        assert!(links.contains(&Link::new(
+            Url::parse(&seed_link).unwrap(),
+            LinkKind::Navigation
+        )));
+        assert!(links.contains(&Link::new(
+            Url::parse("https://example.com/relative-path").unwrap(),
+            LinkKind::Navigation
+        )));
+    }
+
+    #[test]
+    fn resolve_relative_links() {
+        let body = r#"
+            <html>
+                <head>
+                    <meta http-equiv="refresh" content="0; url=/next">
+                </head>
+                <body>
+                    <a href="about#team">About</a>
+                    <img src="logo.png">
+                    <form action="../search"></form>
+                </body>
+            </html>
+        "#;
+        // Links are resolved against the URL the page was served from after redirects.
+        let requested = Url::parse("https://example.com/old").unwrap();
+        let served = Url::parse("https://example.com/docs/guide/").unwrap();
+        let page = HtmlParser::default().parse(&requested, &served, body.to_string());
+        let expected = [
+            ("https://example.com/next", LinkKind::Navigation),
+            ("https://example.com/docs/guide/about", LinkKind::Navigation),
+            ("https://example.com/docs/guide/logo.png", LinkKind::Asset),
+            ("https://example.com/docs/search", LinkKind::Form),
+        ];
+        assert_eq!(page.links.len(), expected.len());
+        for (url, kind) in expected {
+            assert!(page
+                .links
+                .contains(&Link::new(Url::parse(url).unwrap(), kind)));
+        }
+
+        // A `<base href>` takes precedence over the page's URL.
+        let body = r#"<base href="/static/"><a href="page">Page</a>"#;
+        let page = HtmlParser::default().parse(&requested, &served, body.to_string());
+        let urls: Vec<_> = page.links.iter().map(|link| link.url.as_str()).collect();
+        assert_eq!(urls, vec!["https://example.com/static/page"]);
+    }
+
+    #[test]
+    fn extract_link_kinds() {
+        let body = r#"
+            <html>
+                <head>
+                    <meta http-equiv="refresh" content="5; url='https://example.com/moved'">
+                    <link rel="stylesheet" href="https://example.com/style.css">
+                    <script src="https://example.com/app.js"></script>
+                </head>
+                <body>
+                    <map><area href="https://example.com/area"></map>
+                    <img src="https://example.com/a.png" srcset="https://example.com/b.png 2x, https://example.com/c.png 3x">
+                    <video><source src="https://example.com/d.mp4"></video>
+                    <iframe src="https://example.com/embed"></iframe>
+                    <form action="https://example.com/search"></form>
+                </body>
+            </html>
+        "#;
+        let links = HtmlParser::extract_links(
+            &Url::parse("https://example.com/").unwrap(),
+            &Html::parse_document(body),
+        );
+        let expected = [
+            ("https://example.com/moved", LinkKind::Navigation),
+            ("https://example.com/area", LinkKind::Navigation),
+            ("https://example.com/style.css", LinkKind::Asset),
+            ("https://example.com/app.js", LinkKind::Asset),
+            ("https://example.com/a.png", LinkKind::Asset),
+            ("https://example.com/b.png", LinkKind::Asset),
+            ("https://example.com/c.png", LinkKind::Asset),
+            ("https://example.com/d.mp4", LinkKind::Asset),
+            ("https://example.com/embed", LinkKind::Embed),
+            ("https://example.com/search", LinkKind::Form),
+        ];
+        assert_eq!(links.len(), expected.len());
+        for (url, kind) in expected {
+            assert!(links.contains(&Link::new(Url::parse(url).unwrap(), kind)));
+        }
+    }
+
+    #[test]
+    fn extract_link_text() {
+        let body = r#"
+            <html>
+                <body>
+                    <a href="https://example.com/about">About
+                        <em>us</em></a>
+                    <a href="https://example.com/logo"><img src="https://example.com/logo.png" alt="Logo"></a>
+                </body>
+            </html>
+        "#;
+        let links = HtmlParser::extract_links(
+            &Url::parse("https://example.com/").unwrap(),
+            &Html::parse_document(body),
+        );
+        let text = |url: &str| {
+            links
+                .iter()
+                .find(|link| link.url.as_str() == url)
+                .and_then(|link| link.text.clone())
+        };
+        assert_eq!(
+            text("https://example.com/about").as_deref(),
+            Some("About us")
+        );
+        assert_eq!(text("https://example.com/logo"), None);
+        assert_eq!(
+            text("https://example.com/logo.png").as_deref(),
+            Some("Logo")
+        );
+    }
+
+    #[test]
+    fn extract_robots_directives() {
+        let body = r#"
+            <html>
+                <head><meta name="ROBOTS" content="noindex, follow"></head>
+                <body>
+                    <a href="https://example.com/followed">Followed</a>
+                    <a href="https://example.com/sponsored" rel="sponsored nofollow">Not followed</a>
+                </body>
+            </html>
+        "#;
+        let url = Url::parse("https://example.com/").unwrap();
+        let page = HtmlParser::default().parse(&url, &url, body.to_string());
+        assert!(page.robots.noindex);
+        assert!(!page.robots.nofollow);
+
+        let nofollow: Vec<&Link> = page.links.iter().filter(|link| link.nofollow).collect();
+        assert_eq!(nofollow.len(), 1);
+        assert_eq!(nofollow[0].url.as_str(), "https://example.com/sponsored");
+    }
+
+    #[test]
+    fn extract_canonical_and_hreflang() {
+        let body = r#"
+            <html>
+                <head>
+                    <link rel="canonical" href="https://example.com/en/">
+                    <link rel="alternate" hreflang="en" href="https://example.com/en/">
+                    <link rel="alternate" hreflang="FR" href="/fr/">
+                    <link rel="alternate" type="application/rss+xml" href="https://example.com/feed">
+                </head>
+            </html>
+        "#;
+        let url = Url::parse("https://example.com/").unwrap();
+        let page = HtmlParser::default().parse(&url, &url, body.to_string());
+        assert_eq!(page.canonical.unwrap().as_str(), "https://example.com/en/");
+        assert_eq!(page.hreflang.len(), 2);
+        assert_eq!(page.hreflang["fr"].as_str(), "https://example.com/fr/");
+    }
+
+    #[test]
+    fn resolve_relative_canonical() {
+        let body = r#"<link rel="canonical" href="../en/page">"#;
+        let url = Url::parse("https://example.com/fr/page?sort=asc").unwrap();
+        let page = HtmlParser::default().parse(&url, &url, body.to_string());
+        assert_eq!(
+            page.canonical.unwrap().as_str(),
+            "https://example.com/en/page"
+        );
+    }
+
+    #[test]
+    fn extract_login_form() {
+        let url = Url::parse("https://example.com/account/login").unwrap();
+        let body = r#"
+            <form action="/search"><input name="q"></form>
+            <form id="login" action="session" method="POST">
+                <input type="hidden" name="csrf_token" value="abc123">
+                <input name="username" value="">
+                <input type="password" name="password">
+                <input type="checkbox" name="remember" checked>
+                <input type="checkbox" name="newsletter">
+                <select name="region"><option value="eu">EU</option><option value="us" selected>US</option></select>
+                <textarea name="note">hi</textarea>
+                <input type="submit" name="go" value="Log in">
+            </form>
+        "#;
+
+        let forms =
+            HtmlParser::extract_forms(&url, body, "form:has(input[type=password])").unwrap();
+        assert_eq!(
+            forms,
+            vec![Form {
+                action: Url::parse("https://example.com/account/session").unwrap(),
+                method: Method::POST,
+                fields: [
+                    ("csrf_token", "abc123"),
+                    ("username", ""),
+                    ("password", ""),
+                    ("remember", "on"),
+                    ("region", "us"),
+                    ("note", "hi"),
+                ]
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .to_vec(),
+            }]
+        );
+
+        let forms = HtmlParser::extract_forms(&url, body, "form").unwrap();
+        assert_eq!(forms.len(), 2);
+        assert_eq!(forms[0].method, Method::GET);
+        assert!(HtmlParser::extract_forms(&url, body, "form[").is_none());
     }
 }
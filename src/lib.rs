@@ -1,7 +1,28 @@
+pub mod audit;
+pub mod bloom_store;
+pub mod checkpoint;
+pub mod config;
+pub mod config_file;
+pub mod cookie_jar;
 pub mod crawler;
+pub mod diff;
+pub mod disk_store;
+pub mod duplicate;
+pub mod edge_list;
 pub mod html_downloader;
 pub mod html_parser;
+pub mod login;
+pub mod netrc;
+pub mod output;
 pub mod printer;
+pub mod rate_limiter;
+pub mod report;
+pub mod results;
+pub mod sharded_store;
+pub mod shutdown;
+pub mod site_graph;
+pub mod site_tree;
+pub mod sitemap;
 pub mod store;
 pub mod types;
 pub mod url_filter;
@@ -0,0 +1,146 @@
+use crate::{
+    config::LoginConfig,
+    html_downloader::{HtmlDownloader, HtmlPage},
+    html_parser::HtmlParser,
+    types::CrawlerError,
+};
+
+/// Selects the login form when none is configured.
+const DEFAULT_FORM: &str = "form:has(input[type=password])";
+
+/// Logs in by filling and submitting a form, before the crawl starts.
+pub struct Login;
+
+impl Login {
+    /// Fetch the login page, fill the form's fields, and submit it with any hidden fields such
+    /// as a CSRF token. The downloader keeps the session cookies set along the way, so they are
+    /// sent with every later request.
+    pub async fn submit(
+        downloader: &HtmlDownloader,
+        config: &LoginConfig,
+    ) -> Result<HtmlPage, CrawlerError> {
+        let error = |message: String| CrawlerError::LoginError(message);
+        let page = downloader
+            .fetch(config.url.clone())
+            .await
+            .map_err(|e| error(e.to_string()))?;
+        if !page.status.is_success() {
+            return Err(error(format!(
+                "{} responded with {}",
+                config.url, page.status
+            )));
+        }
+
+        let selector = config.form.as_deref().unwrap_or(DEFAULT_FORM);
+        let mut form = HtmlParser::extract_forms(&page.url, &page.body, selector)
+            .ok_or_else(|| error(format!("{} isn't a valid CSS selector", selector)))?
+            .into_iter()
+            .next()
+            .ok_or_else(|| error(format!("no form matching {} on {}", selector, page.url)))?;
+        for (name, value) in &config.fields {
+            form.set(name, value);
+        }
+
+        let page = downloader
+            .submit(&form)
+            .await
+            .map_err(|e| error(e.to_string()))?;
+        if page.status.is_client_error() || page.status.is_server_error() {
+            return Err(error(format!(
+                "{} responded with {}",
+                form.action, page.status
+            )));
+        }
+        Ok(page)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Login;
+    use crate::{config::LoginConfig, html_downloader::HtmlDownloader, types::CrawlerError};
+    use std::collections::BTreeMap;
+    use url::Url;
+    use wiremock::matchers::{body_string, header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    async fn build_site(mock_server: &MockServer) {
+        Mock::given(method("GET"))
+            .and(path("/login"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .append_header("Set-Cookie", "csrf_session=xyz; Path=/")
+                    .set_body_string(
+                        r#"<form method="post" action="/session">
+                            <input type="hidden" name="csrf_token" value="abc123">
+                            <input name="username">
+                            <input type="password" name="password">
+                        </form>"#,
+                    ),
+            )
+            .mount(mock_server)
+            .await;
+        // The token and the cookie set with it must both be sent back.
+        Mock::given(method("POST"))
+            .and(path("/session"))
+            .and(header("Cookie", "csrf_session=xyz"))
+            .and(body_string(
+                "csrf_token=abc123&username=ci&password=s3cret%21",
+            ))
+            .respond_with(
+                ResponseTemplate::new(303)
+                    .append_header("Set-Cookie", "session=valid; Path=/")
+                    .append_header("Location", "/account"),
+            )
+            .mount(mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/account"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(mock_server)
+            .await;
+    }
+
+    fn config(mock_server: &MockServer, password: &str) -> LoginConfig {
+        LoginConfig {
+            url: Url::parse(&format!("{}/login", mock_server.uri())).unwrap(),
+            form: None,
+            fields: BTreeMap::from([
+                ("username".to_string(), "ci".to_string()),
+                ("password".to_string(), password.to_string()),
+            ]),
+        }
+    }
+
+    #[tokio::test]
+    async fn submit_login_form() {
+        let mock_server = MockServer::start().await;
+        build_site(&mock_server).await;
+        let downloader = HtmlDownloader::default();
+
+        let page = Login::submit(&downloader, &config(&mock_server, "s3cret!"))
+            .await
+            .unwrap();
+
+        assert_eq!(page.url.path(), "/account");
+        let cookies = downloader.cookies().cookies(&page.url);
+        assert!(cookies.contains(&("session".to_string(), "valid".to_string())));
+    }
+
+    #[tokio::test]
+    async fn report_failed_login() {
+        let mock_server = MockServer::start().await;
+        build_site(&mock_server).await;
+        let downloader = HtmlDownloader::default();
+
+        let error = Login::submit(&downloader, &config(&mock_server, "wrong"))
+            .await
+            .unwrap_err();
+        assert!(matches!(error, CrawlerError::LoginError(_)));
+
+        let mut config = config(&mock_server, "s3cret!");
+        config.form = Some("form#missing".to_string());
+        let error = Login::submit(&downloader, &config).await.unwrap_err();
+        assert!(error.to_string().contains("no form matching form#missing"));
+    }
+}
@@ -1,24 +1,463 @@
-use crawler::Crawler;
-use std::env;
-use types::CrawlerError;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use reqwest::header::{HeaderName, HeaderValue};
+use std::{
+    fs::File,
+    future::Future,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    process::ExitCode,
+    time::Duration,
+};
 use url::Url;
+use web_crawler_rs::{
+    config::{
+        ClientConfig, Config, OutputFormat, OutputOrder, Scope, TreeGrouping, DEFAULT_USER_AGENT,
+    },
+    config_file::ConfigFile,
+    crawler::Crawler,
+    diff::CrawlDiff,
+    html_downloader::HtmlDownloader,
+    netrc::Netrc,
+    output::NullSink,
+    printer::Printer,
+    results::CrawlResults,
+    shutdown::Shutdown,
+    sitemap::Sitemap,
+    types::CrawlerError,
+    url_filter::UrlFilter,
+};
 
-mod crawler;
-mod html_downloader;
-mod html_parser;
-mod printer;
-mod store;
-mod types;
-mod url_filter;
+/// A web crawler which is designed to crawl a single domain.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Crawl a site, printing each page visited and the links found on it.
+    Crawl(CrawlCommand),
+    /// Crawl a site and report the links to pages which responded with an error, including
+    /// links to other sites, which are checked but not crawled. Exits with status 1 if any are
+    /// found.
+    CheckLinks {
+        /// The URL to start crawling from, unless given in the config file.
+        seed: Option<Url>,
+        #[command(flatten)]
+        options: CrawlOptions,
+    },
+    /// Crawl a site and print an XML sitemap of the pages which can be indexed.
+    Sitemap {
+        /// The URL to start crawling from, unless given in the config file.
+        seed: Option<Url>,
+        #[command(flatten)]
+        options: CrawlOptions,
+    },
+    /// Compare two crawls saved with `crawl --save`.
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        /// Write to this file instead of stdout.
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<PathBuf>,
+    },
+    /// Check whether robots.txt allows a URL to be crawled. Exits with status 1 if not.
+    RobotsTest {
+        url: Url,
+        /// The user agent whose robots.txt rules are checked.
+        #[arg(long, default_value = DEFAULT_USER_AGENT)]
+        user_agent: String,
+    },
+}
+
+#[derive(Args)]
+struct CrawlCommand {
+    /// The URL to start crawling from, unless given in the config file.
+    seed: Option<Url>,
+    #[command(flatten)]
+    options: CrawlOptions,
+    /// How each visited page is printed. [default: text]
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+    /// The order pages are printed in. [default: visited]
+    #[arg(long, value_enum)]
+    order: Option<Order>,
+    /// Save progress to this directory every 30 seconds.
+    #[arg(long, value_name = "DIR")]
+    checkpoint: Option<PathBuf>,
+    /// Continue the crawl saved in this checkpoint directory.
+    #[arg(long, value_name = "DIR", conflicts_with = "seed")]
+    resume: Option<PathBuf>,
+    /// Save the results to this file once the crawl completes.
+    #[arg(long, value_name = "FILE")]
+    save: Option<PathBuf>,
+    /// Only download pages which changed since the crawl saved to this file.
+    #[arg(long, value_name = "FILE")]
+    previous: Option<PathBuf>,
+    /// Export every link between pages to this CSV, or TSV, file.
+    #[arg(long, value_name = "FILE")]
+    edges: Option<PathBuf>,
+    /// Export the status and depth of every page to this CSV, or TSV, file.
+    #[arg(long, value_name = "FILE")]
+    nodes: Option<PathBuf>,
+    /// Export the link graph to this Graphviz DOT, or GraphML, file.
+    #[arg(long, value_name = "FILE")]
+    graph: Option<PathBuf>,
+    /// Collapse pages in the exported graph sharing this many path segments.
+    #[arg(long, value_name = "DEPTH")]
+    collapse: Option<usize>,
+}
+
+/// Options shared by every command which crawls a site. Each takes precedence over the same
+/// setting in the config file.
+#[derive(Args)]
+struct CrawlOptions {
+    /// Read settings from this TOML file.
+    #[arg(long, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// The most pages fetched at once.
+    #[arg(short, long, value_parser = parse_concurrency)]
+    concurrency: Option<usize>,
+    /// The most links followed from the seed URL.
+    #[arg(short, long)]
+    depth: Option<usize>,
+    /// Which links are followed, relative to the seed URL. [default: host]
+    #[arg(long, value_enum)]
+    scope: Option<ScopeArg>,
+    /// Write to this file instead of stdout.
+    #[arg(short, long, value_name = "FILE")]
+    output: Option<PathBuf>,
+    /// The User-Agent header, whose first word is also matched in robots.txt.
+    #[arg(long)]
+    user_agent: Option<String>,
+    /// Give up on a request which hasn't completed within this many seconds.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    timeout: Option<Duration>,
+    /// Give up on a request which hasn't connected within this many seconds.
+    #[arg(long, value_name = "SECONDS", value_parser = parse_seconds)]
+    connect_timeout: Option<Duration>,
+    /// The most requests per second made to each host.
+    #[arg(long, value_name = "REQUESTS", value_parser = parse_rate)]
+    rate_limit: Option<f64>,
+    /// Send this header with every request to the site being crawled, e.g.
+    /// "X-Preview-Key: abc". Can be repeated.
+    #[arg(short = 'H', long = "header", value_name = "HEADER", value_parser = parse_header)]
+    headers: Vec<(String, String)>,
+    /// Send requests through this proxy, e.g. http://proxy:3128 or socks5h://proxy:1080.
+    /// Defaults to $HTTPS_PROXY or $HTTP_PROXY.
+    #[arg(long, value_name = "URL")]
+    proxy: Option<Url>,
+    /// Reach these hosts, domains or IP ranges directly instead of through the proxy, including
+    /// one from the environment, separated by commas.
+    #[arg(long, value_name = "HOSTS", value_delimiter = ',')]
+    no_proxy: Vec<String>,
+    /// Trust the CA certificates in this PEM file as well as the system's.
+    #[arg(long, value_name = "FILE")]
+    ca_bundle: Option<PathBuf>,
+    /// Send the cookies in this Netscape cookies.txt file, and save every cookie held to it once
+    /// the crawl completes.
+    #[arg(long, value_name = "FILE")]
+    cookies: Option<PathBuf>,
+    /// Send the credentials in this netrc file to the machines it lists. Defaults to $NETRC, or
+    /// .netrc in the home directory.
+    #[arg(long, value_name = "FILE", require_equals = true)]
+    netrc: Option<Option<PathBuf>>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Text,
+    Jsonl,
+    Csv,
+    Tree,
+    DiscoveryTree,
+    Html,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Order {
+    Visited,
+    Discovery,
+    Url,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ScopeArg {
+    Host,
+    Domain,
+    Prefix,
+}
+
+impl CrawlOptions {
+    /// The settings in the config file, if any, overridden by those given on the command line.
+    fn settings(&self, seed: Option<Url>) -> Result<ConfigFile, CrawlerError> {
+        let mut file = match &self.config {
+            Some(path) => ConfigFile::load(path)?,
+            None => ConfigFile::default(),
+        };
+        file.seed = seed.or(file.seed);
+        file.concurrency = self.concurrency.or(file.concurrency);
+        file.depth = self.depth.or(file.depth);
+        file.output = self.output.clone().or(file.output);
+        file.user_agent = self.user_agent.clone().or(file.user_agent);
+        let seconds = |timeout: Option<Duration>| timeout.map(|timeout| timeout.as_secs_f64());
+        file.timeout = seconds(self.timeout).or(file.timeout);
+        file.connect_timeout = seconds(self.connect_timeout).or(file.connect_timeout);
+        file.rate_limit = self.rate_limit.or(file.rate_limit);
+        file.headers.extend(self.headers.iter().cloned());
+        file.proxy = self.proxy.clone().or(file.proxy);
+        file.no_proxy.extend(self.no_proxy.iter().cloned());
+        file.ca_bundle = self.ca_bundle.clone().or(file.ca_bundle);
+        file.cookies = self.cookies.clone().or(file.cookies);
+        if let Some(path) = &self.netrc {
+            file.netrc = path.clone().or_else(Netrc::default_path);
+        }
+        if let Some(scope) = self.scope {
+            file.scope = Some(match scope {
+                ScopeArg::Host => Scope::Host,
+                ScopeArg::Domain => Scope::Domain,
+                ScopeArg::Prefix => Scope::Prefix,
+            });
+        }
+        Ok(file)
+    }
+}
 
 #[tokio::main]
-async fn main() -> Result<(), CrawlerError> {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        eprintln!("{}", CrawlerError::InputMalformed);
-        return Err(CrawlerError::InputMalformed);
+async fn main() -> ExitCode {
+    match run(Cli::parse().command).await {
+        Ok(code) => code,
+        Err(error) => {
+            eprintln!("{}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(command: Command) -> Result<ExitCode, CrawlerError> {
+    match command {
+        Command::Crawl(command) => crawl(command).await,
+        Command::CheckLinks { seed, options } => {
+            let settings = options.settings(seed)?;
+            let seed = settings.seed.clone().ok_or(CrawlerError::InputMalformed)?;
+            let config = Config {
+                check_external_links: true,
+                ..settings.config()?
+            };
+            let shutdown = config.shutdown.clone();
+            let crawl = Crawler::run_with_sink(seed, config, NullSink);
+            let results = until_interrupted(&shutdown, crawl).await?;
+            print_summary(&results)?;
+            let broken = results.broken_links();
+            let mut wrt = output(&settings.output)?;
+            Printer::print_broken_links(&mut wrt, &broken).map_err(output_error)?;
+            wrt.flush().map_err(output_error)?;
+            Ok(if broken.is_empty() {
+                ExitCode::SUCCESS
+            } else {
+                ExitCode::FAILURE
+            })
+        }
+        Command::Sitemap { seed, options } => {
+            let settings = options.settings(seed)?;
+            let seed = settings.seed.clone().ok_or(CrawlerError::InputMalformed)?;
+            let config = settings.config()?;
+            let shutdown = config.shutdown.clone();
+            let scope = config.scope;
+            let crawl = Crawler::run_with_sink(seed.clone(), config, NullSink);
+            let results = until_interrupted(&shutdown, crawl).await?;
+            print_summary(&results)?;
+            let mut wrt = output(&settings.output)?;
+            Sitemap::new(&results, &seed, scope)
+                .write(&mut wrt)
+                .and_then(|_| wrt.flush())
+                .map_err(output_error)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::Diff {
+            old,
+            new,
+            output: path,
+        } => {
+            let load = |path: PathBuf| {
+                CrawlResults::load(&path).map_err(|_| CrawlerError::ResultsError { path })
+            };
+            let diff = CrawlDiff::between(&load(old)?, &load(new)?);
+            let mut wrt = output(&path)?;
+            Printer::print_diff(&mut wrt, &diff)
+                .and_then(|_| wrt.flush())
+                .map_err(output_error)?;
+            Ok(ExitCode::SUCCESS)
+        }
+        Command::RobotsTest { url, user_agent } => robots_test(url, user_agent).await,
+    }
+}
+
+async fn crawl(command: CrawlCommand) -> Result<ExitCode, CrawlerError> {
+    // A seed given on the command line takes precedence over resuming from the config file.
+    let seed_given = command.seed.is_some();
+    let mut settings = command.options.settings(command.seed)?;
+    if let Some(format) = command.format {
+        settings.format = Some(match format {
+            Format::Text => OutputFormat::Text,
+            Format::Jsonl => OutputFormat::JsonLines,
+            Format::Csv => OutputFormat::Csv,
+            Format::Tree => OutputFormat::Tree(TreeGrouping::Path),
+            Format::DiscoveryTree => OutputFormat::Tree(TreeGrouping::Discovery),
+            Format::Html => OutputFormat::Html,
+        });
+    }
+    if let Some(order) = command.order {
+        settings.order = Some(match order {
+            Order::Visited => OutputOrder::Visited,
+            Order::Discovery => OutputOrder::Discovery,
+            Order::Url => OutputOrder::Url,
+        });
+    }
+    settings.checkpoint = command.checkpoint.or(settings.checkpoint);
+    settings.resume = command.resume.or(settings.resume.filter(|_| !seed_given));
+    settings.save = command.save.or(settings.save);
+    settings.previous = command.previous.or(settings.previous);
+    settings.edges = command.edges.or(settings.edges);
+    settings.nodes = command.nodes.or(settings.nodes);
+    settings.graph = command.graph.or(settings.graph);
+    settings.collapse = command.collapse.or(settings.collapse);
+
+    let config = settings.config()?;
+    let shutdown = config.shutdown.clone();
+    let sink = config.format.sink(output(&settings.output)?);
+    let results = match (settings.seed, settings.resume) {
+        (_, Some(dir)) => {
+            until_interrupted(&shutdown, Crawler::resume_with_sink(dir, config, sink)).await?
+        }
+        (Some(seed), None) => {
+            until_interrupted(&shutdown, Crawler::run_with_sink(seed, config, sink)).await?
+        }
+        (None, None) => return Err(CrawlerError::InputMalformed),
+    };
+    print_summary(&results)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print how the crawl went to stderr, apart from the output.
+fn print_summary(results: &CrawlResults) -> Result<(), CrawlerError> {
+    Printer::print_summary(io::stderr(), results).map_err(output_error)
+}
+
+/// Run the crawl, shutting it down gracefully on the first SIGINT or SIGTERM and exiting the
+/// process on the second. Signals are only handled until the crawl returns.
+async fn until_interrupted<T>(shutdown: &Shutdown, crawl: impl Future<Output = T>) -> T {
+    let shutdown = shutdown.clone();
+    let listener = tokio::spawn(async move {
+        if wait_for_signal().await.is_err() {
+            return;
+        }
+        eprintln!("Shutting down, waiting for pages in progress. Interrupt again to exit now.");
+        shutdown.trigger();
+
+        if wait_for_signal().await.is_ok() {
+            std::process::exit(130);
+        }
+    });
+    let result = crawl.await;
+    listener.abort();
+    result
+}
+
+#[cfg(unix)]
+async fn wait_for_signal() -> io::Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result,
+        _ = terminate.recv() => Ok(()),
+    }
+}
+
+#[cfg(not(unix))]
+async fn wait_for_signal() -> io::Result<()> {
+    tokio::signal::ctrl_c().await
+}
+
+/// Fetch robots.txt for the URL's host and print whether it allows the URL to be crawled.
+async fn robots_test(url: Url, user_agent: String) -> Result<ExitCode, CrawlerError> {
+    let subdomain = url
+        .host_str()
+        .ok_or(CrawlerError::SubdomainError { url: url.clone() })?
+        .to_string();
+    let client = ClientConfig {
+        user_agent: user_agent.clone(),
+        ..Default::default()
+    };
+    let downloader = HtmlDownloader::new(&client)?;
+    let robots_txt = downloader
+        .fetch(url.join("/robots.txt")?)
+        .await
+        .map_err(|error| CrawlerError::ClientError(error.to_string()))?;
+
+    // Everything is allowed when there is no robots.txt.
+    let robots_txt = if robots_txt.status.is_success() {
+        robots_txt.body
+    } else {
+        String::new()
+    };
+    let filter = UrlFilter::new(subdomain, robots_txt).with_user_agent(&user_agent);
+    if filter.allowed(&url) {
+        println!("allowed {}", url);
+        Ok(ExitCode::SUCCESS)
+    } else {
+        println!("disallowed {}", url);
+        Ok(ExitCode::FAILURE)
     }
+}
+
+/// Open the file to write to, or stdout if none was given.
+fn output(path: &Option<PathBuf>) -> Result<Box<dyn Write + Send>, CrawlerError> {
+    match path {
+        Some(path) => {
+            let file = File::create(path).map_err(output_error)?;
+            Ok(Box::new(BufWriter::new(file)))
+        }
+        None => Ok(Box::new(io::stdout())),
+    }
+}
+
+fn output_error(error: io::Error) -> CrawlerError {
+    CrawlerError::OutputError(error.to_string())
+}
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("{} isn't a number of seconds", value))
+}
+
+fn parse_header(value: &str) -> Result<(String, String), String> {
+    let (name, value) = value
+        .split_once(':')
+        .ok_or_else(|| format!("{} isn't in the form \"Name: value\"", value))?;
+    let (name, value) = (name.trim(), value.trim());
+    HeaderName::try_from(name).map_err(|error| format!("{}: {}", name, error))?;
+    HeaderValue::try_from(value).map_err(|error| format!("{}: {}", name, error))?;
+    Ok((name.to_string(), value.to_string()))
+}
+
+fn parse_concurrency(value: &str) -> Result<usize, String> {
+    value
+        .parse::<usize>()
+        .ok()
+        .filter(|concurrency| *concurrency > 0)
+        .ok_or_else(|| format!("{} isn't a number more than 0", value))
+}
 
-    let seed_url = Url::parse(&args[1].clone())?;
-    Crawler::run(seed_url).await
+fn parse_rate(value: &str) -> Result<f64, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .filter(|rate| rate.is_finite() && *rate > 0.0)
+        .ok_or_else(|| format!("{} isn't a number more than 0", value))
 }
@@ -0,0 +1,145 @@
+use crate::config::{Credentials, HostConfig};
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Logins read from a `.netrc` file, as used by curl and ftp. The `default` entry is ignored,
+/// so that credentials are only ever sent to the machines they are listed for.
+#[derive(Debug, Default, PartialEq)]
+pub struct Netrc {
+    machines: BTreeMap<String, Credentials>,
+}
+
+impl Netrc {
+    /// The file named by the `NETRC` environment variable, otherwise `.netrc` in the home
+    /// directory.
+    pub fn default_path() -> Option<PathBuf> {
+        env::var_os("NETRC")
+            .map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".netrc")))
+    }
+
+    /// Read the logins in a file.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// Read the `machine`, `login` and `password` tokens, separated by any whitespace. Macros
+    /// defined with `macdef` are skipped.
+    pub fn parse(text: &str) -> Self {
+        let mut machines = BTreeMap::new();
+        let mut machine: Option<String> = None;
+        let mut login = None;
+        let mut password = None;
+        let mut finish = |machine: Option<String>, login: Option<String>, password| {
+            if let (Some(machine), Some(username)) = (machine, login) {
+                machines.insert(machine, Credentials::Basic { username, password });
+            }
+        };
+
+        let mut lines = text.lines();
+        while let Some(line) = lines.next() {
+            let mut tokens = line.split_whitespace();
+            while let Some(token) = tokens.next() {
+                match token {
+                    "machine" | "default" => {
+                        finish(machine.take(), login.take(), password.take());
+                        if token == "machine" {
+                            machine = tokens.next().map(str::to_string);
+                        }
+                    }
+                    "login" => login = tokens.next().map(str::to_string),
+                    "password" => password = tokens.next().map(str::to_string),
+                    "account" => {
+                        tokens.next();
+                    }
+                    "macdef" => {
+                        // The macro runs until the next blank line.
+                        for line in lines.by_ref() {
+                            if line.trim().is_empty() {
+                                break;
+                            }
+                        }
+                        break;
+                    }
+                    _ if token.starts_with('#') => break,
+                    _ => {}
+                }
+            }
+        }
+        finish(machine, login, password);
+        Netrc { machines }
+    }
+
+    /// The credentials listed for the host.
+    pub fn credentials(&self, host: &str) -> Option<&Credentials> {
+        self.machines.get(host)
+    }
+
+    /// Use the login for each machine with `https` requests to it, unless the host already has
+    /// credentials configured.
+    pub fn apply(&self, hosts: &mut BTreeMap<String, HostConfig>) {
+        for (machine, credentials) in &self.machines {
+            let host = hosts.entry(machine.clone()).or_default();
+            if host.credentials.is_none() {
+                host.credentials = Some(credentials.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Netrc;
+    use crate::config::{Credentials, HostConfig};
+    use std::collections::BTreeMap;
+
+    const NETRC: &str = "# Internal sites\n\
+        machine docs.example.com login ci password s3cret\n\
+        machine wiki.example.com\n  login reader\n  password hunter2\n  account ops\n\
+        macdef init\nmachine evil.example.com login x password y\n\n\
+        default login anonymous password guest\n";
+
+    #[test]
+    fn parse_machines() {
+        let netrc = Netrc::parse(NETRC);
+        assert_eq!(
+            netrc.credentials("docs.example.com"),
+            Some(&Credentials::Basic {
+                username: "ci".to_string(),
+                password: Some("s3cret".to_string())
+            })
+        );
+        assert_eq!(
+            netrc.credentials("wiki.example.com"),
+            Some(&Credentials::Basic {
+                username: "reader".to_string(),
+                password: Some("hunter2".to_string())
+            })
+        );
+        // Neither the macro's contents nor the default login are used.
+        assert_eq!(netrc.credentials("evil.example.com"), None);
+        assert_eq!(netrc.machines.len(), 2);
+    }
+
+    #[test]
+    fn apply_to_hosts() {
+        let configured = Credentials::Bearer("token".to_string());
+        let mut hosts = BTreeMap::from([(
+            "docs.example.com".to_string(),
+            HostConfig {
+                credentials: Some(configured.clone()),
+                ..Default::default()
+            },
+        )]);
+        Netrc::parse(NETRC).apply(&mut hosts);
+
+        assert_eq!(hosts["docs.example.com"].credentials, Some(configured));
+        assert!(matches!(
+            hosts["wiki.example.com"].credentials,
+            Some(Credentials::Basic { .. })
+        ));
+    }
+}
@@ -0,0 +1,475 @@
+use crate::{
+    config::{OutputFormat, OutputOrder, TreeGrouping},
+    printer::Printer,
+    report::HtmlReport,
+    results::PageResult,
+    site_tree::SiteTree,
+};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+use url::Url;
+
+/// Somewhere to write each page as it is visited. The crawler writes to a single sink from one
+/// task, so implementations don't need to worry about interleaved output.
+pub trait OutputSink: Send {
+    fn write(&mut self, page: &PageResult) -> io::Result<()>;
+
+    /// Called once every page has been written.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<S: OutputSink + ?Sized> OutputSink for Box<S> {
+    fn write(&mut self, page: &PageResult) -> io::Result<()> {
+        (**self).write(page)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        (**self).finish()
+    }
+}
+
+impl OutputFormat {
+    /// A sink which writes pages in this format.
+    pub fn sink(self, wrt: impl Write + Send + 'static) -> Box<dyn OutputSink> {
+        match self {
+            OutputFormat::Text => Box::new(TextSink::new(wrt)),
+            OutputFormat::JsonLines => Box::new(JsonLinesSink::new(wrt)),
+            OutputFormat::Csv => Box::new(CsvSink::new(wrt)),
+            OutputFormat::Tree(grouping) => Box::new(TreeSink::new(wrt, grouping)),
+            OutputFormat::Html => Box::new(HtmlReportSink::new(wrt)),
+        }
+    }
+}
+
+/// Holds back every page until the crawl completes, then writes them to another sink in a
+/// consistent order, so that two crawls of the same site produce the same output.
+pub struct SortedSink {
+    inner: Box<dyn OutputSink>,
+    order: OutputOrder,
+    pages: Vec<PageResult>,
+}
+
+impl SortedSink {
+    pub fn new(inner: Box<dyn OutputSink>, order: OutputOrder) -> Self {
+        SortedSink {
+            inner,
+            order,
+            pages: Vec::new(),
+        }
+    }
+
+    /// Wrap the sink when the order isn't the order pages are visited in.
+    pub fn wrap(inner: Box<dyn OutputSink>, order: OutputOrder) -> Box<dyn OutputSink> {
+        match order {
+            OutputOrder::Visited => inner,
+            order => Box::new(SortedSink::new(inner, order)),
+        }
+    }
+
+    /// Order the pages breadth first from the seed URL, following links in URL order. Pages
+    /// which can't be reached that way, e.g. from an earlier crawl, follow by depth then URL.
+    fn discovery_order(pages: Vec<PageResult>) -> Vec<PageResult> {
+        let mut seeds: Vec<_> = pages
+            .iter()
+            .filter(|page| page.depth == 0)
+            .map(|page| page.url.clone())
+            .collect();
+        seeds.sort();
+        let mut remaining: HashMap<_, _> = pages
+            .into_iter()
+            .map(|page| (page.url.clone(), page))
+            .collect();
+
+        let mut ordered = Vec::new();
+        let mut queue = VecDeque::from(seeds);
+        while let Some(url) = queue.pop_front() {
+            let Some(page) = remaining.remove(&url) else {
+                continue;
+            };
+            let mut links: Vec<_> = page.links.iter().map(|link| &link.url).collect();
+            links.sort();
+            queue.extend(
+                links
+                    .into_iter()
+                    .filter(|url| remaining.contains_key(url))
+                    .cloned(),
+            );
+            ordered.push(page);
+        }
+
+        let mut unreached: Vec<_> = remaining.into_values().collect();
+        unreached.sort_by(|a, b| (a.depth, &a.url).cmp(&(b.depth, &b.url)));
+        ordered.extend(unreached);
+        ordered
+    }
+}
+
+impl OutputSink for SortedSink {
+    fn write(&mut self, page: &PageResult) -> io::Result<()> {
+        self.pages.push(page.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let mut pages = std::mem::take(&mut self.pages);
+        match self.order {
+            OutputOrder::Visited => {}
+            OutputOrder::Discovery => pages = SortedSink::discovery_order(pages),
+            OutputOrder::Url => pages.sort_by(|a, b| a.url.cmp(&b.url)),
+        }
+        for page in &pages {
+            self.inner.write(page)?;
+        }
+        self.inner.finish()
+    }
+}
+
+/// Each successfully fetched URL followed by its links, as written by `Printer::print`.
+pub struct TextSink<W> {
+    wrt: W,
+}
+
+impl<W: Write> TextSink<W> {
+    pub fn new(wrt: W) -> Self {
+        TextSink { wrt }
+    }
+}
+
+impl<W: Write + Send> OutputSink for TextSink<W> {
+    fn write(&mut self, page: &PageResult) -> io::Result<()> {
+        if page.is_success() {
+            Printer::print(&mut self.wrt, page)?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.wrt.flush()
+    }
+}
+
+/// One JSON object per page, as written by `Printer::print_json`.
+pub struct JsonLinesSink<W> {
+    wrt: W,
+}
+
+impl<W: Write> JsonLinesSink<W> {
+    pub fn new(wrt: W) -> Self {
+        JsonLinesSink { wrt }
+    }
+}
+
+impl<W: Write + Send> OutputSink for JsonLinesSink<W> {
+    fn write(&mut self, page: &PageResult) -> io::Result<()> {
+        Printer::print_json(&mut self.wrt, page)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.wrt.flush()
+    }
+}
+
+/// One row per page, after a header row. Links are counted rather than listed; see
+/// `EdgeList` for a row per link.
+pub struct CsvSink<W: Write> {
+    writer: csv::Writer<W>,
+}
+
+#[derive(Serialize)]
+struct CsvRecord<'a> {
+    url: &'a Url,
+    final_url: &'a Url,
+    status: Option<u16>,
+    error: Option<&'a str>,
+    depth: usize,
+    referrer: Option<&'a Url>,
+    content_type: Option<&'a str>,
+    response_time_ms: u64,
+    size: usize,
+    content_hash: Option<&'a str>,
+    links: usize,
+}
+
+impl<W: Write> CsvSink<W> {
+    pub fn new(wrt: W) -> Self {
+        CsvSink {
+            writer: csv::Writer::from_writer(wrt),
+        }
+    }
+}
+
+impl<W: Write + Send> OutputSink for CsvSink<W> {
+    fn write(&mut self, page: &PageResult) -> io::Result<()> {
+        self.writer.serialize(CsvRecord {
+            url: &page.url,
+            final_url: &page.final_url,
+            status: page.status.map(|status| status.as_u16()),
+            error: page.error.as_deref(),
+            depth: page.depth,
+            referrer: page.referrer.as_ref(),
+            content_type: page.content_type.as_deref(),
+            response_time_ms: page.response_time_ms,
+            size: page.size,
+            content_hash: page.content_hash.as_deref(),
+            links: page.links.len(),
+        })?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// Holds back every page until the crawl completes, then writes them as a `SiteTree`.
+pub struct TreeSink<W> {
+    wrt: W,
+    grouping: TreeGrouping,
+    pages: Vec<PageResult>,
+}
+
+impl<W: Write> TreeSink<W> {
+    pub fn new(wrt: W, grouping: TreeGrouping) -> Self {
+        TreeSink {
+            wrt,
+            grouping,
+            pages: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write + Send> OutputSink for TreeSink<W> {
+    fn write(&mut self, page: &PageResult) -> io::Result<()> {
+        self.pages.push(page.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let tree = match self.grouping {
+            TreeGrouping::Path => SiteTree::by_path(&self.pages),
+            TreeGrouping::Discovery => SiteTree::by_discovery(&self.pages),
+        };
+        tree.write(&mut self.wrt)?;
+        self.wrt.flush()
+    }
+}
+
+/// Holds back every page until the crawl completes, then writes them as an `HtmlReport`.
+pub struct HtmlReportSink<W> {
+    wrt: W,
+    pages: Vec<PageResult>,
+}
+
+impl<W: Write> HtmlReportSink<W> {
+    pub fn new(wrt: W) -> Self {
+        HtmlReportSink {
+            wrt,
+            pages: Vec::new(),
+        }
+    }
+}
+
+impl<W: Write + Send> OutputSink for HtmlReportSink<W> {
+    fn write(&mut self, page: &PageResult) -> io::Result<()> {
+        self.pages.push(page.clone());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        HtmlReport::new(std::mem::take(&mut self.pages)).write(&mut self.wrt)?;
+        self.wrt.flush()
+    }
+}
+
+/// Discards every page, for when only the results of the crawl are needed.
+pub struct NullSink;
+
+impl OutputSink for NullSink {
+    fn write(&mut self, _page: &PageResult) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps every page in memory, so that library users can inspect the output. Clones share the
+/// same pages, so keep a clone to read them once the crawl completes.
+#[derive(Clone, Default)]
+pub struct MemorySink {
+    pages: Arc<Mutex<Vec<PageResult>>>,
+}
+
+impl MemorySink {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pages written so far, in the order they were written.
+    pub fn pages(&self) -> Vec<PageResult> {
+        match self.pages.lock() {
+            Ok(pages) => pages.clone(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+impl OutputSink for MemorySink {
+    fn write(&mut self, page: &PageResult) -> io::Result<()> {
+        match self.pages.lock() {
+            Ok(mut pages) => {
+                pages.push(page.clone());
+                Ok(())
+            }
+            Err(_) => Err(io::Error::other("MemorySink lock poisoned")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MemorySink, OutputSink, SortedSink};
+    use crate::{
+        config::{OutputFormat, OutputOrder, TreeGrouping},
+        results::PageResult,
+        types::{Link, LinkKind},
+    };
+    use reqwest::StatusCode;
+    use std::{
+        io::{self, Write},
+        sync::{Arc, Mutex},
+    };
+    use url::Url;
+
+    /// A writer which can be read after being moved into a sink.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn pages() -> Vec<PageResult> {
+        let url = Url::parse("https://example.com/").unwrap();
+        let missing = url.join("missing").unwrap();
+        vec![
+            PageResult::new(url.clone(), url, StatusCode::OK),
+            PageResult::new(missing.clone(), missing, StatusCode::NOT_FOUND),
+        ]
+    }
+
+    fn write_all(format: OutputFormat) -> String {
+        let buffer = SharedBuffer::default();
+        let mut sink = format.sink(buffer.clone());
+        for page in pages() {
+            sink.write(&page).unwrap();
+        }
+        sink.finish().unwrap();
+        buffer.contents()
+    }
+
+    #[test]
+    fn text_skips_errors() {
+        assert_eq!(write_all(OutputFormat::Text), "https://example.com/\n\n");
+    }
+
+    #[test]
+    fn json_lines() {
+        assert_eq!(write_all(OutputFormat::JsonLines).lines().count(), 2);
+    }
+
+    #[test]
+    fn csv() {
+        let output = write_all(OutputFormat::Csv);
+        let mut lines = output.lines();
+        assert_eq!(
+            lines.next(),
+            Some("url,final_url,status,error,depth,referrer,content_type,response_time_ms,size,content_hash,links")
+        );
+        assert_eq!(
+            lines.nth(1),
+            Some("https://example.com/missing,https://example.com/missing,404,,0,,,0,0,,0")
+        );
+    }
+
+    #[test]
+    fn tree() {
+        assert_eq!(
+            write_all(OutputFormat::Tree(TreeGrouping::Path)),
+            "https://example.com/ [200] (1)\n└── missing [404] (0)\n"
+        );
+    }
+
+    #[test]
+    fn memory() {
+        let sink = MemorySink::new();
+        let mut writer = sink.clone();
+        for page in pages() {
+            writer.write(&page).unwrap();
+        }
+        assert_eq!(sink.pages().len(), 2);
+    }
+
+    fn write_sorted(order: OutputOrder) -> Vec<String> {
+        let seed = Url::parse("https://example.com/").unwrap();
+        let page = |path: &str, depth: usize, links: &[&str]| {
+            let url = seed.join(path).unwrap();
+            let mut page = PageResult::new(url.clone(), url, StatusCode::OK);
+            page.depth = depth;
+            for link in links {
+                let link = seed.join(link).unwrap();
+                page.links.insert(Link::new(link, LinkKind::Navigation));
+            }
+            page
+        };
+
+        let memory = MemorySink::new();
+        let mut sink = SortedSink::wrap(Box::new(memory.clone()), order);
+        // Written in the order they finished, rather than the order they were found.
+        for page in [
+            page("/b/c", 2, &[]),
+            page("/z", 1, &["/b/c"]),
+            page("/", 0, &["/z", "/b"]),
+            page("/b", 1, &["/b/c"]),
+        ] {
+            sink.write(&page).unwrap();
+        }
+        sink.finish().unwrap();
+        memory
+            .pages()
+            .into_iter()
+            .map(|page| page.url.path().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn discovery_order() {
+        assert_eq!(
+            write_sorted(OutputOrder::Discovery),
+            vec!["/", "/b", "/z", "/b/c"]
+        );
+    }
+
+    #[test]
+    fn url_order() {
+        assert_eq!(
+            write_sorted(OutputOrder::Url),
+            vec!["/", "/b", "/b/c", "/z"]
+        );
+    }
+}
@@ -1,21 +1,278 @@
+use crate::{
+    audit::AuditIssue,
+    diff::CrawlDiff,
+    results::{CrawlResults, PageResult},
+    types::Link,
+};
+
+use reqwest::StatusCode;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+};
 use url::Url;
 
-use std::{collections::HashSet, io};
+/// The version of the JSON Lines schema, bumped whenever a field is changed or removed.
+pub const JSON_SCHEMA_VERSION: u32 = 2;
+
+/// A visited page as written by `Printer::print_json`.
+#[derive(Serialize)]
+struct JsonRecord<'a> {
+    schema_version: u32,
+    url: &'a Url,
+    final_url: &'a Url,
+    status: Option<u16>,
+    error: Option<&'a str>,
+    depth: usize,
+    referrer: Option<&'a Url>,
+    content_type: Option<&'a str>,
+    response_time_ms: u64,
+    size: usize,
+    content_hash: Option<&'a str>,
+    duplicate_of: Option<&'a Url>,
+    changed: Option<bool>,
+    links: Vec<&'a Link>,
+}
 
 #[derive(Default)]
 pub struct Printer;
 
-/// Print the URL visited and a list of links found on that page.
+/// Print the URL visited and a list of links found on that page, sorted by URL.
 impl Printer {
-    pub fn print(mut wrt: impl io::Write, url: Url, links: &HashSet<Url>) -> io::Result<()> {
+    pub fn print(mut wrt: impl io::Write, page: &PageResult) -> io::Result<()> {
         let mut buffer = String::new();
 
-        buffer.push_str(&format!("{}\n", url));
+        buffer.push_str(page.url.as_str());
+        if let Some(original) = &page.duplicate_of {
+            buffer.push_str(&format!(" duplicate of {}", original));
+        }
+        match page.changed {
+            Some(true) => buffer.push_str(" (changed)"),
+            Some(false) => buffer.push_str(" (unchanged)"),
+            None => {}
+        }
+        buffer.push('\n');
+        let mut links: Vec<_> = page.links.iter().collect();
+        links.sort();
         for link in links {
-            buffer.push_str(&format!("-- {}\n", link));
+            buffer.push_str(&format!("-- {}\n", link.url));
         }
 
         writeln!(wrt, "{}", buffer)?;
         Ok(())
     }
+
+    /// Print the page as a single line of JSON, with its links sorted by URL.
+    pub fn print_json(mut wrt: impl io::Write, page: &PageResult) -> io::Result<()> {
+        let mut links: Vec<_> = page.links.iter().collect();
+        links.sort();
+        let record = JsonRecord {
+            schema_version: JSON_SCHEMA_VERSION,
+            url: &page.url,
+            final_url: &page.final_url,
+            status: page.status.map(|status| status.as_u16()),
+            error: page.error.as_deref(),
+            depth: page.depth,
+            referrer: page.referrer.as_ref(),
+            content_type: page.content_type.as_deref(),
+            response_time_ms: page.response_time_ms,
+            size: page.size,
+            content_hash: page.content_hash.as_deref(),
+            duplicate_of: page.duplicate_of.as_ref(),
+            changed: page.changed,
+            links,
+        };
+        serde_json::to_writer(&mut wrt, &record)?;
+        writeln!(wrt)
+    }
+
+    /// Print a summary of the crawl, including how many URLs were left unvisited.
+    pub fn print_summary(mut wrt: impl io::Write, results: &CrawlResults) -> io::Result<()> {
+        let summary = results.summary();
+        let pages = results.pages();
+        let errors = pages.iter().filter(|page| !page.is_success()).count();
+        if summary.interrupted {
+            write!(wrt, "Interrupted: ")?;
+        }
+        write!(
+            wrt,
+            "visited {} page(s) with {} error(s) in {:.1}s, {} URL(s) left unvisited",
+            pages.len(),
+            errors,
+            summary.elapsed.as_secs_f64(),
+            summary.unvisited
+        )?;
+        let changed = pages
+            .iter()
+            .filter(|page| page.changed == Some(true))
+            .count();
+        if pages.iter().any(|page| page.changed.is_some()) {
+            write!(wrt, ", {} changed since the previous crawl", changed)?;
+        }
+        writeln!(wrt)
+    }
+
+    /// Print the differences between two crawls, one change per line.
+    pub fn print_diff(mut wrt: impl io::Write, diff: &CrawlDiff) -> io::Result<()> {
+        for url in &diff.added {
+            writeln!(wrt, "+ page {}", url)?;
+        }
+        for url in &diff.removed {
+            writeln!(wrt, "- page {}", url)?;
+        }
+        for (url, (old, new)) in &diff.status_changes {
+            let (old, new) = (Self::status(*old), Self::status(*new));
+            writeln!(wrt, "~ status {} {} -> {}", url, old, new)?;
+        }
+        for url in &diff.content_changes {
+            writeln!(wrt, "~ content {}", url)?;
+        }
+        for (page, link) in &diff.added_edges {
+            writeln!(wrt, "+ link {} -> {}", page, link)?;
+        }
+        for (page, link) in &diff.removed_edges {
+            writeln!(wrt, "- link {} -> {}", page, link)?;
+        }
+        Ok(())
+    }
+
+    /// Print each broken link followed by the pages linking to it.
+    pub fn print_broken_links(
+        mut wrt: impl io::Write,
+        broken: &BTreeMap<Url, (Option<StatusCode>, BTreeSet<Url>)>,
+    ) -> io::Result<()> {
+        writeln!(wrt, "Broken links: {} found", broken.len())?;
+        for (url, (status, referrers)) in broken {
+            writeln!(wrt, "{} {}", Self::status(*status), url)?;
+            for referrer in referrers {
+                writeln!(wrt, "-- {}", referrer)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The status code as a number, or `unreachable` when there is none.
+    fn status(status: Option<StatusCode>) -> String {
+        match status {
+            Some(status) => status.as_u16().to_string(),
+            None => "unreachable".to_string(),
+        }
+    }
+
+    /// Print the issues found by auditing the crawl.
+    pub fn print_audit(mut wrt: impl io::Write, issues: &[AuditIssue]) -> io::Result<()> {
+        writeln!(wrt, "Audit: {} issue(s) found", issues.len())?;
+        for issue in issues {
+            writeln!(wrt, "-- {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Printer;
+    use crate::{
+        results::{CrawlResults, CrawlSummary, PageResult},
+        types::{Link, LinkKind},
+    };
+    use reqwest::StatusCode;
+    use std::time::Duration;
+    use url::Url;
+
+    fn print(page: &PageResult) -> String {
+        let mut output = Vec::new();
+        Printer::print(&mut output, page).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn print_page() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let mut page = PageResult::new(url.clone(), url.clone(), StatusCode::OK);
+        page.links.insert(Link::new(
+            url.join("careers").unwrap(),
+            LinkKind::Navigation,
+        ));
+        page.links
+            .insert(Link::new(url.join("about").unwrap(), LinkKind::Navigation));
+        assert_eq!(
+            print(&page),
+            "https://example.com/\n-- https://example.com/about\n-- https://example.com/careers\n\n"
+        );
+    }
+
+    #[test]
+    fn print_json() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let mut page = PageResult::new(url.clone(), url.clone(), StatusCode::OK);
+        page.depth = 1;
+        page.referrer = Some(url.join("home").unwrap());
+        page.content_type = Some("text/html".to_string());
+        page.size = 42;
+        page.links
+            .insert(Link::new(url.join("b").unwrap(), LinkKind::Asset));
+        page.links
+            .insert(Link::new(url.join("b").unwrap(), LinkKind::Navigation));
+        page.links
+            .insert(Link::new(url.join("a").unwrap(), LinkKind::Navigation));
+
+        let mut output = Vec::new();
+        Printer::print_json(&mut output, &page).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(output.lines().count(), 1);
+
+        let record: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(record["schema_version"], 2);
+        assert_eq!(record["status"], 200);
+        assert_eq!(record["error"], serde_json::Value::Null);
+        assert_eq!(record["depth"], 1);
+        assert_eq!(record["referrer"], "https://example.com/home");
+        assert_eq!(record["content_type"], "text/html");
+        assert_eq!(record["size"], 42);
+        assert_eq!(record["links"][0]["url"], "https://example.com/a");
+        assert_eq!(record["links"][1]["kind"], "navigation");
+        assert_eq!(record["links"][2]["url"], "https://example.com/b");
+        assert_eq!(record["links"][2]["kind"], "asset");
+    }
+
+    #[test]
+    fn print_duplicate() {
+        let url = Url::parse("https://example.com/index.html").unwrap();
+        let mut page = PageResult::new(url.clone(), url.clone(), StatusCode::OK);
+        page.duplicate_of = Some(Url::parse("https://example.com/").unwrap());
+        page.links
+            .insert(Link::new(url.join("about").unwrap(), LinkKind::Navigation));
+        assert_eq!(
+            print(&page),
+            "https://example.com/index.html duplicate of https://example.com/\n-- https://example.com/about\n\n"
+        );
+    }
+
+    #[test]
+    fn print_summary() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let results = CrawlResults::new();
+        results.insert(PageResult::new(url.clone(), url.clone(), StatusCode::OK));
+        let missing = url.join("missing").unwrap();
+        results.insert(PageResult::new(
+            missing.clone(),
+            missing,
+            StatusCode::NOT_FOUND,
+        ));
+        results.set_summary(CrawlSummary {
+            unvisited: 3,
+            elapsed: Duration::from_millis(1500),
+            interrupted: true,
+            ..Default::default()
+        });
+
+        let mut output = Vec::new();
+        Printer::print_summary(&mut output, &results).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "Interrupted: visited 2 page(s) with 1 error(s) in 1.5s, 3 URL(s) left unvisited\n"
+        );
+    }
 }
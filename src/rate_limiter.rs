@@ -0,0 +1,102 @@
+use crate::config::host_entry;
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Mutex,
+    time::Duration,
+};
+use tokio::time::{self, Instant};
+use url::Url;
+
+/// Spaces out requests to each host, so that no more than the configured number are started
+/// per second.
+#[derive(Default)]
+pub struct RateLimiter {
+    /// The time between requests to hosts without an interval of their own.
+    interval: Option<Duration>,
+    hosts: BTreeMap<String, Duration>,
+    /// The earliest time the next request to each host may start.
+    next: Mutex<HashMap<String, Instant>>,
+}
+
+impl RateLimiter {
+    /// Limit every host to the given number of requests per second. Unlimited when unset.
+    pub fn new(rate: Option<f64>) -> Self {
+        RateLimiter {
+            interval: rate.and_then(Self::interval),
+            ..Default::default()
+        }
+    }
+
+    /// Limit requests to the host, or host and port, to the given number per second, instead of
+    /// the default.
+    pub fn with_host(mut self, host: &str, rate: f64) -> Self {
+        if let Some(interval) = Self::interval(rate) {
+            self.hosts.insert(host.to_string(), interval);
+        }
+        self
+    }
+
+    fn interval(rate: f64) -> Option<Duration> {
+        Duration::try_from_secs_f64(1.0 / rate).ok()
+    }
+
+    /// Wait until a request to the URL's host is allowed, reserving its slot.
+    pub async fn wait(&self, url: &Url) {
+        let host = url.host_str().unwrap_or_default();
+        let Some(interval) = host_entry(&self.hosts, url).copied().or(self.interval) else {
+            return;
+        };
+        let start = {
+            let mut next = match self.next.lock() {
+                Ok(next) => next,
+                Err(_) => {
+                    eprintln!("RateLimiter lock poisoned");
+                    // Let the request through rather than hold up the crawl.
+                    return;
+                }
+            };
+            let now = Instant::now();
+            let start = next.get(host).copied().unwrap_or(now).max(now);
+            next.insert(host.to_string(), start + interval);
+            start
+        };
+        time::sleep_until(start).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RateLimiter;
+    use std::time::Duration;
+    use tokio::time::Instant;
+    use url::Url;
+
+    #[tokio::test(start_paused = true)]
+    async fn space_out_requests_per_host() {
+        let limiter = RateLimiter::new(Some(20.0)).with_host("fast.example.com", 1000.0);
+        let slow = Url::parse("https://example.com/").unwrap();
+        let fast = Url::parse("https://fast.example.com/").unwrap();
+
+        let started = Instant::now();
+        for _ in 0..3 {
+            limiter.wait(&fast).await;
+        }
+        assert_eq!(started.elapsed(), Duration::from_millis(2));
+        for _ in 0..3 {
+            limiter.wait(&slow).await;
+        }
+        // The first request starts straight away and each after it waits 50ms.
+        assert_eq!(started.elapsed(), Duration::from_millis(102));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn unlimited_by_default() {
+        let limiter = RateLimiter::default();
+        let url = Url::parse("https://example.com/").unwrap();
+        let started = Instant::now();
+        for _ in 0..10 {
+            limiter.wait(&url).await;
+        }
+        assert_eq!(started.elapsed(), Duration::ZERO);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::results::PageResult;
+use html_escape::{encode_double_quoted_attribute, encode_text};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, BTreeSet},
+    io::{self, Write},
+};
+use url::Url;
+
+/// The number of pages listed in the slowest pages section.
+const SLOWEST_PAGES: usize = 10;
+
+/// A single static HTML page summarising a crawl, with the styles and script inline so that
+/// it can be attached to a ticket and opened anywhere.
+pub struct HtmlReport {
+    pages: Vec<PageResult>,
+}
+
+impl HtmlReport {
+    /// Report on the pages, sorted by URL.
+    pub fn new(mut pages: Vec<PageResult>) -> Self {
+        pages.sort_by(|a, b| a.url.cmp(&b.url));
+        HtmlReport { pages }
+    }
+
+    /// Write the report: summary stats, a sortable table of pages, broken links with the
+    /// pages linking to them, and the slowest pages.
+    pub fn write(&self, mut wrt: impl Write) -> io::Result<()> {
+        writeln!(wrt, "<!DOCTYPE html>")?;
+        writeln!(wrt, "<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">")?;
+        writeln!(wrt, "<title>Crawl report</title>\n<style>{}</style>", STYLE)?;
+        writeln!(wrt, "</head>\n<body>\n<h1>Crawl report</h1>")?;
+        self.write_summary(&mut wrt)?;
+        self.write_pages(&mut wrt)?;
+        self.write_broken_links(&mut wrt)?;
+        self.write_slowest(&mut wrt)?;
+        writeln!(wrt, "<script>{}</script>\n</body>\n</html>", SCRIPT)
+    }
+
+    fn write_summary(&self, wrt: &mut impl Write) -> io::Result<()> {
+        let count =
+            |filter: fn(&PageResult) -> bool| self.pages.iter().filter(|p| filter(p)).count();
+        writeln!(wrt, "<h2>Summary</h2>\n<table class=\"summary\">")?;
+        for (label, value) in [
+            ("Pages", self.pages.len()),
+            ("Errors", count(PageResult::is_broken)),
+            ("Redirects", count(PageResult::redirected)),
+            ("Duplicates", count(|page| page.duplicate_of.is_some())),
+        ] {
+            writeln!(wrt, "<tr><th>{}</th><td>{}</td></tr>", label, value)?;
+        }
+        writeln!(wrt, "</table>")?;
+
+        let mut depths: BTreeMap<usize, usize> = BTreeMap::new();
+        for page in &self.pages {
+            *depths.entry(page.depth).or_insert(0) += 1;
+        }
+        writeln!(wrt, "<h3>Pages by depth</h3>\n<table class=\"summary\">")?;
+        writeln!(wrt, "<tr><th>Depth</th><th>Pages</th></tr>")?;
+        for (depth, pages) in depths {
+            writeln!(wrt, "<tr><td>{}</td><td>{}</td></tr>", depth, pages)?;
+        }
+        writeln!(wrt, "</table>")
+    }
+
+    fn write_pages(&self, wrt: &mut impl Write) -> io::Result<()> {
+        writeln!(wrt, "<h2>Pages</h2>\n<table class=\"sortable\">")?;
+        write!(wrt, "<thead><tr>")?;
+        for (heading, numeric) in [
+            ("URL", false),
+            ("Status", true),
+            ("Depth", true),
+            ("Content type", false),
+            ("Size (bytes)", true),
+            ("Time (ms)", true),
+            ("Links", true),
+        ] {
+            write!(wrt, "<th data-numeric=\"{}\">{}</th>", numeric, heading)?;
+        }
+        writeln!(wrt, "</tr></thead>\n<tbody>")?;
+        for page in &self.pages {
+            writeln!(
+                wrt,
+                "<tr class=\"{}\"><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                Self::status_class(page),
+                Self::link(&page.url),
+                Self::status(page),
+                page.depth,
+                encode_text(page.content_type.as_deref().unwrap_or("")),
+                page.size,
+                page.response_time_ms,
+                page.links.len()
+            )?;
+        }
+        writeln!(wrt, "</tbody>\n</table>")
+    }
+
+    fn write_broken_links(&self, wrt: &mut impl Write) -> io::Result<()> {
+        // Every visited page linking to each URL.
+        let mut referrers: BTreeMap<&Url, BTreeSet<&Url>> = BTreeMap::new();
+        for page in &self.pages {
+            for link in &page.links {
+                referrers.entry(&link.url).or_default().insert(&page.url);
+            }
+        }
+
+        let broken: Vec<_> = self.pages.iter().filter(|page| page.is_broken()).collect();
+        writeln!(wrt, "<h2>Broken links ({})</h2>", broken.len())?;
+        if broken.is_empty() {
+            return writeln!(wrt, "<p>No broken links found.</p>");
+        }
+        writeln!(
+            wrt,
+            "<table>\n<tr><th>URL</th><th>Status</th><th>Linked from</th></tr>"
+        )?;
+        for page in broken {
+            let mut linked_from = referrers.get(&page.url).cloned().unwrap_or_default();
+            linked_from.extend(page.referrer.as_ref());
+            let linked_from: Vec<_> = linked_from.into_iter().map(Self::link).collect();
+            writeln!(
+                wrt,
+                "<tr class=\"error\"><td>{}</td><td>{}</td><td>{}</td></tr>",
+                Self::link(&page.url),
+                Self::status(page),
+                linked_from.join("<br>")
+            )?;
+        }
+        writeln!(wrt, "</table>")
+    }
+
+    fn write_slowest(&self, wrt: &mut impl Write) -> io::Result<()> {
+        let mut slowest: Vec<_> = self.pages.iter().collect();
+        slowest.sort_by_key(|page| Reverse(page.response_time_ms));
+        writeln!(wrt, "<h2>Slowest pages</h2>\n<table>")?;
+        writeln!(wrt, "<tr><th>URL</th><th>Time (ms)</th></tr>")?;
+        for page in slowest.into_iter().take(SLOWEST_PAGES) {
+            writeln!(
+                wrt,
+                "<tr><td>{}</td><td>{}</td></tr>",
+                Self::link(&page.url),
+                page.response_time_ms
+            )?;
+        }
+        writeln!(wrt, "</table>")
+    }
+
+    fn link(url: &Url) -> String {
+        format!(
+            "<a href=\"{}\">{}</a>",
+            encode_double_quoted_attribute(url.as_str()),
+            encode_text(url.as_str())
+        )
+    }
+
+    /// The page's status code, or why it couldn't be fetched.
+    fn status(page: &PageResult) -> String {
+        match (page.status, &page.error) {
+            (Some(status), _) => status.as_u16().to_string(),
+            (None, error) => encode_text(error.as_deref().unwrap_or("unreachable")).into_owned(),
+        }
+    }
+
+    fn status_class(page: &PageResult) -> &'static str {
+        if page.is_success() {
+            "ok"
+        } else if page.status.is_some_and(|status| status.is_redirection()) {
+            "redirect"
+        } else {
+            "error"
+        }
+    }
+}
+
+const STYLE: &str = "
+body { font-family: sans-serif; margin: 2em; color: #222; }
+table { border-collapse: collapse; margin-bottom: 1.5em; }
+th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }
+table.sortable th { cursor: pointer; background: #f0f0f0; }
+tr.error td { background: #fde0dc; }
+tr.redirect td { background: #e3f0fb; }
+";
+
+/// Sort the table by a column when its heading is clicked, toggling between ascending and
+/// descending order.
+const SCRIPT: &str = "
+document.querySelectorAll('table.sortable').forEach(function (table) {
+  table.querySelectorAll('th').forEach(function (th, column) {
+    th.addEventListener('click', function () {
+      var numeric = th.dataset.numeric === 'true';
+      var ascending = th.dataset.order !== 'asc';
+      th.dataset.order = ascending ? 'asc' : 'desc';
+      var body = table.tBodies[0];
+      var rows = Array.from(body.rows);
+      rows.sort(function (a, b) {
+        var x = a.cells[column].textContent, y = b.cells[column].textContent;
+        var order = numeric ? x - y : x.localeCompare(y);
+        return ascending ? order : -order;
+      });
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});
+";
+
+#[cfg(test)]
+mod tests {
+    use super::HtmlReport;
+    use crate::{
+        results::PageResult,
+        types::{Link, LinkKind},
+    };
+    use reqwest::StatusCode;
+    use url::Url;
+
+    fn render(pages: Vec<PageResult>) -> String {
+        let mut output = Vec::new();
+        HtmlReport::new(pages).write(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn report() {
+        let seed = Url::parse("https://example.com/").unwrap();
+        let missing = seed.join("missing?a=1&b=<2>").unwrap();
+        let mut home = PageResult::new(seed.clone(), seed.clone(), StatusCode::OK);
+        home.response_time_ms = 250;
+        home.links
+            .insert(Link::new(missing.clone(), LinkKind::Navigation));
+        let mut broken = PageResult::new(missing.clone(), missing, StatusCode::NOT_FOUND);
+        broken.depth = 1;
+        // Redirects aren't errors, but pages which couldn't be fetched are.
+        let moved = seed.join("moved").unwrap();
+        let moved = PageResult::new(moved.clone(), moved, StatusCode::MOVED_PERMANENTLY);
+        let unreachable = PageResult::failed(seed.join("timeout").unwrap(), "timed out".into());
+
+        let report = render(vec![broken, home, moved, unreachable]);
+        assert!(report.starts_with("<!DOCTYPE html>"));
+        assert!(report.contains("<tr><th>Pages</th><td>4</td></tr>"));
+        assert!(report.contains("<tr><th>Errors</th><td>2</td></tr>"));
+        assert!(report.contains("<h2>Broken links (2)</h2>"));
+        // The broken link lists the page linking to it, with the URL escaped.
+        assert!(report.contains(
+            "<tr class=\"error\"><td><a href=\"https://example.com/missing?a=1&amp;b=%3C2%3E\">https://example.com/missing?a=1&amp;b=%3C2%3E</a></td><td>404</td><td><a href=\"https://example.com/\">https://example.com/</a></td></tr>"
+        ));
+        assert!(report.contains("<tr><td><a href=\"https://example.com/\">https://example.com/</a></td><td>250</td></tr>"));
+        // Nothing is loaded from elsewhere.
+        assert!(!report.contains("<link") && !report.contains("src="));
+    }
+}
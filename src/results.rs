@@ -0,0 +1,400 @@
+use crate::{
+    audit::AuditIssue,
+    html_downloader::Validators,
+    types::{Link, RobotsDirectives},
+};
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use url::Url;
+
+/// The outcome of visiting a single page.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PageResult {
+    pub url: Url,
+    /// The URL the content was served from, after following any redirects.
+    pub final_url: Url,
+    /// The status of the response, or `None` if the page couldn't be fetched.
+    #[serde(with = "status_code")]
+    pub status: Option<StatusCode>,
+    /// Why the page couldn't be fetched, such as a timeout or a refused connection.
+    #[serde(default)]
+    pub error: Option<String>,
+    /// The SHA-256 hash of the body.
+    pub content_hash: Option<String>,
+    pub links: HashSet<Link>,
+    pub canonical: Option<Url>,
+    pub hreflang: BTreeMap<String, Url>,
+    /// The earlier page whose content this page duplicates.
+    pub duplicate_of: Option<Url>,
+    /// Directives from `<meta name="robots">` and the `X-Robots-Tag` header.
+    #[serde(default)]
+    pub robots: RobotsDirectives,
+    /// The `ETag` response header.
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// The `Last-Modified` response header.
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    /// Whether the content changed since the previous crawl. Unknown without a previous crawl.
+    #[serde(default)]
+    pub changed: Option<bool>,
+    /// The number of links followed from the seed URL to reach this page.
+    #[serde(default)]
+    pub depth: usize,
+    /// The page this URL was first found on. Unset for the seed URL.
+    #[serde(default)]
+    pub referrer: Option<Url>,
+    /// The `Content-Type` response header.
+    #[serde(default)]
+    pub content_type: Option<String>,
+    /// The time taken to fetch the page, in milliseconds.
+    #[serde(default)]
+    pub response_time_ms: u64,
+    /// The size of the body in bytes.
+    #[serde(default)]
+    pub size: usize,
+}
+
+impl PageResult {
+    pub fn new(url: Url, final_url: Url, status: StatusCode) -> Self {
+        PageResult {
+            url,
+            final_url,
+            status: Some(status),
+            error: None,
+            content_hash: None,
+            links: HashSet::new(),
+            canonical: None,
+            hreflang: BTreeMap::new(),
+            duplicate_of: None,
+            robots: RobotsDirectives::default(),
+            etag: None,
+            last_modified: None,
+            changed: None,
+            depth: 0,
+            referrer: None,
+            content_type: None,
+            response_time_ms: 0,
+            size: 0,
+        }
+    }
+
+    /// The result of a page which couldn't be fetched, with the reason why.
+    pub fn failed(url: Url, error: String) -> Self {
+        PageResult {
+            status: None,
+            error: Some(error),
+            ..PageResult::new(url.clone(), url, StatusCode::OK)
+        }
+    }
+
+    /// Determine whether the page responded with a success status.
+    pub fn is_success(&self) -> bool {
+        self.status.is_some_and(|status| status.is_success())
+    }
+
+    /// Determine whether the page is broken: it responded with a client or server error, or
+    /// couldn't be fetched at all.
+    pub fn is_broken(&self) -> bool {
+        self.status
+            .is_none_or(|status| status.is_client_error() || status.is_server_error())
+    }
+
+    /// The validators to send when requesting this page again.
+    pub fn validators(&self) -> Validators {
+        Validators {
+            etag: self.etag.clone(),
+            last_modified: self.last_modified.clone(),
+        }
+    }
+
+    /// Determine whether the request for this page was redirected.
+    pub fn redirected(&self) -> bool {
+        self.url != self.final_url
+    }
+}
+
+/// Serialize a status code as its number, or null when there is none.
+mod status_code {
+    use reqwest::StatusCode;
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        status: &Option<StatusCode>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        status.map(|status| status.as_u16()).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<StatusCode>, D::Error> {
+        Option::<u16>::deserialize(deserializer)?
+            .map(|status| StatusCode::from_u16(status).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// How a crawl went, recorded once it completes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CrawlSummary {
+    /// The number of URLs scheduled but never visited.
+    pub unvisited: usize,
+    pub elapsed: Duration,
+    /// The crawl was shut down before it completed.
+    pub interrupted: bool,
+    /// The problems found by `Config::audit`.
+    pub audit_issues: Vec<AuditIssue>,
+    /// The status of each link target on another host, checked with
+    /// `Config::check_external_links`, or `None` if it couldn't be reached.
+    pub external_links: BTreeMap<Url, Option<StatusCode>>,
+}
+
+/// A thread-safe collection of the pages visited during a crawl, keyed by URL.
+#[derive(Clone, Default)]
+pub struct CrawlResults {
+    pages: Arc<Mutex<HashMap<Url, PageResult>>>,
+    summary: Arc<Mutex<CrawlSummary>>,
+}
+
+impl CrawlResults {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a page, replacing any previous result for the same URL.
+    pub fn insert(&self, page: PageResult) {
+        match self.pages.lock() {
+            Ok(mut pages) => {
+                pages.insert(page.url.clone(), page);
+            }
+            Err(_) => eprintln!("CrawlResults lock poisioned"),
+        }
+    }
+
+    /// Look up the result for a URL.
+    pub fn get(&self, url: &Url) -> Option<PageResult> {
+        self.pages.lock().ok()?.get(url).cloned()
+    }
+
+    /// Return a snapshot of every page visited so far.
+    pub fn pages(&self) -> Vec<PageResult> {
+        match self.pages.lock() {
+            Ok(pages) => pages.values().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Save every page to a JSON file, sorted by URL.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut pages = self.pages();
+        pages.sort_by(|a, b| a.url.cmp(&b.url));
+        serde_json::to_writer(BufWriter::new(fs::File::create(path)?), &pages)?;
+        Ok(())
+    }
+
+    /// Load the pages saved to a JSON file.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let pages: Vec<PageResult> =
+            serde_json::from_reader(BufReader::new(fs::File::open(path)?))?;
+        let results = CrawlResults::new();
+        for page in pages {
+            results.insert(page);
+        }
+        Ok(results)
+    }
+
+    /// Return each duplicate page mapped to the first page seen with the same content.
+    pub fn duplicates(&self) -> BTreeMap<Url, Url> {
+        self.pages()
+            .into_iter()
+            .filter_map(|page| Some((page.url, page.duplicate_of?)))
+            .collect()
+    }
+
+    /// Return each page, or external link target, which responded with a client or server
+    /// error, along with its status and the pages linking to it. Pages and external link
+    /// targets which couldn't be reached have no status.
+    pub fn broken_links(&self) -> BTreeMap<Url, (Option<StatusCode>, BTreeSet<Url>)> {
+        let is_error = |status: StatusCode| status.is_client_error() || status.is_server_error();
+        let pages = self.pages();
+        let mut broken: BTreeMap<_, _> = pages
+            .iter()
+            .filter(|page| page.is_broken())
+            .map(|page| {
+                let referrers: BTreeSet<_> = page.referrer.iter().cloned().collect();
+                (page.url.clone(), (page.status, referrers))
+            })
+            .collect();
+        for (url, status) in self.summary().external_links {
+            if status.is_none_or(is_error) {
+                broken.insert(url, (status, BTreeSet::new()));
+            }
+        }
+        for page in &pages {
+            for link in &page.links {
+                if let Some((_, referrers)) = broken.get_mut(&link.url) {
+                    referrers.insert(page.url.clone());
+                }
+            }
+        }
+        broken
+    }
+
+    /// Record how the crawl went.
+    pub fn set_summary(&self, summary: CrawlSummary) {
+        if let Ok(mut current) = self.summary.lock() {
+            *current = summary;
+        }
+    }
+
+    /// How the crawl went. Empty until the crawl completes.
+    pub fn summary(&self) -> CrawlSummary {
+        self.summary
+            .lock()
+            .map(|summary| summary.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.pages.lock().map(|pages| pages.len()).unwrap_or(0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CrawlResults, CrawlSummary, PageResult};
+    use crate::types::{Link, LinkKind};
+    use reqwest::StatusCode;
+    use std::collections::BTreeMap;
+    use url::Url;
+
+    #[test]
+    fn insert_and_get() {
+        let results = CrawlResults::new();
+        let url = Url::parse("https://example.com/").unwrap();
+        let redirect = Url::parse("https://example.com/home").unwrap();
+        assert!(results.get(&url).is_none());
+
+        results.insert(PageResult::new(
+            url.clone(),
+            redirect.clone(),
+            StatusCode::OK,
+        ));
+        let page = results.get(&url).unwrap();
+        assert!(page.redirected());
+        assert_eq!(results.len(), 1);
+
+        let mut duplicate = PageResult::new(redirect.clone(), redirect.clone(), StatusCode::OK);
+        duplicate.duplicate_of = Some(url.clone());
+        results.insert(duplicate);
+        assert_eq!(results.duplicates().get(&redirect), Some(&url));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let mut page = PageResult::new(url.clone(), url.clone(), StatusCode::OK);
+        page.etag = Some("\"v1\"".to_string());
+        let timed_out = url.join("slow").unwrap();
+        let results = CrawlResults::new();
+        results.insert(page);
+        results.insert(PageResult::failed(
+            timed_out.clone(),
+            "timed out".to_string(),
+        ));
+
+        let file = tempfile::NamedTempFile::new().unwrap();
+        results.save(file.path()).unwrap();
+        let loaded = CrawlResults::load(file.path()).unwrap();
+        assert_eq!(loaded.len(), 2);
+        let page = loaded.get(&url).unwrap();
+        assert_eq!(page.status, Some(StatusCode::OK));
+        assert_eq!(page.validators().etag.unwrap(), "\"v1\"");
+        let failed = loaded.get(&timed_out).unwrap();
+        assert_eq!(failed.status, None);
+        assert_eq!(failed.error.as_deref(), Some("timed out"));
+    }
+
+    #[test]
+    fn broken_links_include_failed_pages() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let slow = url.join("slow").unwrap();
+        let mut home = PageResult::new(url.clone(), url.clone(), StatusCode::OK);
+        home.links
+            .insert(Link::new(slow.clone(), LinkKind::Navigation));
+        let results = CrawlResults::new();
+        results.insert(home);
+        results.insert(PageResult::failed(slow.clone(), "timed out".to_string()));
+
+        let broken = results.broken_links();
+        assert_eq!(broken.len(), 1);
+        let (status, referrers) = &broken[&slow];
+        assert_eq!(*status, None);
+        assert!(referrers.contains(&url));
+    }
+
+    #[test]
+    fn broken_links() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let missing = url.join("missing").unwrap();
+        let mut home = PageResult::new(url.clone(), url.clone(), StatusCode::OK);
+        home.links
+            .insert(Link::new(missing.clone(), LinkKind::Navigation));
+        let results = CrawlResults::new();
+        results.insert(home);
+        results.insert(PageResult::new(
+            missing.clone(),
+            missing.clone(),
+            StatusCode::NOT_FOUND,
+        ));
+
+        let broken = results.broken_links();
+        assert_eq!(broken.len(), 1);
+        let (status, referrers) = &broken[&missing];
+        assert_eq!(*status, Some(StatusCode::NOT_FOUND));
+        assert_eq!(referrers.iter().collect::<Vec<_>>(), vec![&url]);
+    }
+
+    #[test]
+    fn broken_external_links() {
+        let url = Url::parse("https://example.com/").unwrap();
+        let [ok, missing, unreachable] = [
+            "https://ok.com/",
+            "https://missing.com/",
+            "https://down.com/",
+        ]
+        .map(|target| Url::parse(target).unwrap());
+        let mut home = PageResult::new(url.clone(), url.clone(), StatusCode::OK);
+        for target in [&ok, &missing, &unreachable] {
+            home.links
+                .insert(Link::new(target.clone(), LinkKind::Navigation));
+        }
+        let results = CrawlResults::new();
+        results.insert(home);
+        results.set_summary(CrawlSummary {
+            external_links: BTreeMap::from([
+                (ok, Some(StatusCode::OK)),
+                (missing.clone(), Some(StatusCode::GONE)),
+                (unreachable.clone(), None),
+            ]),
+            ..Default::default()
+        });
+
+        let broken = results.broken_links();
+        assert_eq!(broken.len(), 2);
+        assert_eq!(broken[&missing].0, Some(StatusCode::GONE));
+        assert_eq!(broken[&unreachable].0, None);
+        assert!(broken[&unreachable].1.contains(&url));
+    }
+}
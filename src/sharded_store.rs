@@ -0,0 +1,90 @@
+use crate::store::VisitedStore;
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash, RandomState},
+    sync::Mutex,
+    thread,
+};
+
+/// A thread-safe in-memory store split into shards, each behind its own lock. Items are
+/// assigned to a shard by their hash, so concurrent inserts only contend when they land on
+/// the same shard rather than on one global lock.
+pub struct ShardedStore<T, V = ()> {
+    shards: Vec<Mutex<HashMap<T, V>>>,
+    hasher: RandomState,
+}
+
+impl<T: Eq + Hash, V> ShardedStore<T, V> {
+    /// Create a store with the given number of shards, rounded up to a power of two.
+    pub fn new(num_shards: usize) -> Self {
+        let num_shards = num_shards.max(1).next_power_of_two();
+        ShardedStore {
+            shards: (0..num_shards)
+                .map(|_| Mutex::new(HashMap::new()))
+                .collect(),
+            hasher: RandomState::new(),
+        }
+    }
+
+    fn shard(&self, item: &T) -> &Mutex<HashMap<T, V>> {
+        let hash = self.hasher.hash_one(item) as usize;
+        &self.shards[hash & (self.shards.len() - 1)]
+    }
+}
+
+impl<T: Eq + Hash, V> Default for ShardedStore<T, V> {
+    /// A store with four shards per available CPU.
+    fn default() -> Self {
+        let cpus = thread::available_parallelism().map_or(1, |n| n.get());
+        Self::new(cpus * 4)
+    }
+}
+
+impl<T: Eq + Hash + Send, V: Clone + Send> VisitedStore<T, V> for ShardedStore<T, V> {
+    fn insert_or_get(&self, item: T, value: V) -> Option<V> {
+        let mut shard = match self.shard(&item).lock() {
+            Ok(shard) => shard,
+            Err(_) => {
+                eprintln!("ShardedStore lock poisioned");
+                // Report the item as already seen so it isn't processed again.
+                return Some(value);
+            }
+        };
+        match shard.get(&item) {
+            Some(existing) => Some(existing.clone()),
+            None => {
+                shard.insert(item, value);
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ShardedStore;
+    use crate::store::VisitedStore;
+    use std::thread;
+
+    #[test]
+    fn insert() {
+        let store = ShardedStore::new(4);
+        assert!(store.insert("a"));
+        assert!(store.insert("b"));
+        assert!(!store.insert("a"));
+        assert_eq!(store.insert_or_get("c", ()), None);
+    }
+
+    #[test]
+    fn concurrent_insert() {
+        // Each item is reported as new exactly once across all threads.
+        let store: ShardedStore<i32> = ShardedStore::new(8);
+        let new: usize = thread::scope(|scope| {
+            let handles: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| (0..1000).filter(|i| store.insert(*i)).count()))
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).sum()
+        });
+        assert_eq!(new, 1000);
+    }
+}
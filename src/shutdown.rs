@@ -0,0 +1,58 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+/// Signals that a crawl should stop taking new URLs and finish the work in progress. The crawl
+/// only observes it, so it's up to the caller to trigger it, e.g. on Ctrl-C.
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    triggered: Arc<AtomicBool>,
+    /// How long to wait for pages in progress before giving up on them.
+    pub timeout: Duration,
+}
+
+impl Default for Shutdown {
+    /// Wait up to 10 seconds for pages in progress.
+    fn default() -> Self {
+        Shutdown {
+            triggered: Arc::new(AtomicBool::new(false)),
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new(timeout: Duration) -> Self {
+        Shutdown {
+            timeout,
+            ..Default::default()
+        }
+    }
+
+    /// Ask the crawl to shut down.
+    pub fn trigger(&self) {
+        self.triggered.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_triggered(&self) -> bool {
+        self.triggered.load(Ordering::SeqCst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Shutdown;
+
+    #[test]
+    fn trigger() {
+        let shutdown = Shutdown::default();
+        let handle = shutdown.clone();
+        assert!(!shutdown.is_triggered());
+        handle.trigger();
+        assert!(shutdown.is_triggered());
+    }
+}
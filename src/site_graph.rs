@@ -0,0 +1,296 @@
+use crate::results::CrawlResults;
+use html_escape::encode_double_quoted_attribute;
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+use url::Url;
+
+/// The link graph of a crawl, for visualising the structure of a site. Only links between
+/// visited pages are included. Pages can be collapsed into a node per path prefix, so that
+/// large sites stay readable.
+pub struct SiteGraph {
+    nodes: BTreeMap<String, GraphNode>,
+    /// The number of links between each pair of nodes.
+    edges: BTreeMap<(String, String), usize>,
+}
+
+/// One page, or the pages sharing a path prefix when collapsed.
+#[derive(Debug, PartialEq)]
+pub struct GraphNode {
+    pub pages: usize,
+    /// The highest status code of the pages, so that errors stand out, or `None` if any of
+    /// them couldn't be fetched.
+    pub status: Option<u16>,
+}
+
+impl SiteGraph {
+    /// Build the graph with a node per page, or with a node per path prefix of `collapse_depth`
+    /// segments. For example with a depth of 1, `/blog/a` and `/blog/b` become `/blog/*`.
+    pub fn new(results: &CrawlResults, collapse_depth: Option<usize>) -> Self {
+        let pages = results.pages();
+        let mut nodes: BTreeMap<String, GraphNode> = BTreeMap::new();
+        for page in &pages {
+            let node = nodes
+                .entry(Self::node_id(&page.url, collapse_depth))
+                .or_insert(GraphNode {
+                    pages: 0,
+                    status: Some(0),
+                });
+            node.pages += 1;
+            node.status = node
+                .status
+                .zip(page.status)
+                .map(|(highest, status)| highest.max(status.as_u16()));
+        }
+
+        let mut edges = BTreeMap::new();
+        for page in &pages {
+            let source = Self::node_id(&page.url, collapse_depth);
+            for link in &page.links {
+                if results.get(&link.url).is_none() {
+                    continue;
+                }
+                let target = Self::node_id(&link.url, collapse_depth);
+                if source != target {
+                    *edges.entry((source.clone(), target)).or_insert(0) += 1;
+                }
+            }
+        }
+
+        SiteGraph { nodes, edges }
+    }
+
+    /// The node the URL belongs to, keyed by the URL or its collapsed path prefix.
+    fn node_id(url: &Url, collapse_depth: Option<usize>) -> String {
+        let Some(depth) = collapse_depth else {
+            return url.to_string();
+        };
+        let segments: Vec<_> = url
+            .path_segments()
+            .map(|segments| segments.filter(|segment| !segment.is_empty()).collect())
+            .unwrap_or_default();
+        if segments.len() <= depth {
+            return url.to_string();
+        }
+        let prefix = segments[..depth]
+            .iter()
+            .map(|segment| format!("{}/", segment))
+            .collect::<String>();
+        format!("{}/{}*", url.origin().ascii_serialization(), prefix)
+    }
+
+    pub fn node(&self, id: &str) -> Option<&GraphNode> {
+        self.nodes.get(id)
+    }
+
+    /// The number of links from one node to another.
+    pub fn links(&self, source: &str, target: &str) -> usize {
+        let key = (source.to_string(), target.to_string());
+        self.edges.get(&key).copied().unwrap_or(0)
+    }
+
+    /// Write the graph in the Graphviz DOT language.
+    pub fn write_dot(&self, mut wrt: impl Write) -> io::Result<()> {
+        writeln!(wrt, "digraph site {{")?;
+        writeln!(wrt, "    node [shape=box, style=filled];")?;
+        for (id, node) in &self.nodes {
+            let id = Self::escape_dot(id);
+            let label = match node.pages {
+                1 => id.clone(),
+                pages => format!("{}\\n({} pages)", id, pages),
+            };
+            writeln!(
+                wrt,
+                "    \"{}\" [label=\"{}\", fillcolor=\"{}\"];",
+                id,
+                label,
+                Self::colour(node.status)
+            )?;
+        }
+        for ((source, target), links) in &self.edges {
+            write!(
+                wrt,
+                "    \"{}\" -> \"{}\"",
+                Self::escape_dot(source),
+                Self::escape_dot(target)
+            )?;
+            if *links > 1 {
+                write!(wrt, " [label=\"{}\"]", links)?;
+            }
+            writeln!(wrt, ";")?;
+        }
+        writeln!(wrt, "}}")
+    }
+
+    /// Write the graph as GraphML, with the status, page count and colour of each node and the
+    /// number of links along each edge.
+    pub fn write_graphml(&self, mut wrt: impl Write) -> io::Result<()> {
+        writeln!(wrt, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            wrt,
+            r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#
+        )?;
+        for (id, target, kind) in [
+            ("status", "node", "int"),
+            ("pages", "node", "int"),
+            ("color", "node", "string"),
+            ("links", "edge", "int"),
+        ] {
+            writeln!(
+                wrt,
+                r#"  <key id="{0}" for="{1}" attr.name="{0}" attr.type="{2}"/>"#,
+                id, target, kind
+            )?;
+        }
+        writeln!(wrt, r#"  <graph id="site" edgedefault="directed">"#)?;
+        for (id, node) in &self.nodes {
+            // A node with a page which couldn't be fetched has no status.
+            let status = node
+                .status
+                .map(|status| format!(r#"<data key="status">{}</data>"#, status))
+                .unwrap_or_default();
+            writeln!(
+                wrt,
+                r#"    <node id="{}">{}<data key="pages">{}</data><data key="color">{}</data></node>"#,
+                encode_double_quoted_attribute(id),
+                status,
+                node.pages,
+                Self::colour(node.status)
+            )?;
+        }
+        for ((source, target), links) in &self.edges {
+            writeln!(
+                wrt,
+                r#"    <edge source="{}" target="{}"><data key="links">{}</data></edge>"#,
+                encode_double_quoted_attribute(source),
+                encode_double_quoted_attribute(target),
+                links
+            )?;
+        }
+        writeln!(wrt, "  </graph>")?;
+        writeln!(wrt, "</graphml>")
+    }
+
+    /// Write the graph to a file, as GraphML for a `.graphml` file and DOT otherwise.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let mut wrt = BufWriter::new(fs::File::create(path)?);
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some(extension) if extension.eq_ignore_ascii_case("graphml") => {
+                self.write_graphml(&mut wrt)?
+            }
+            _ => self.write_dot(&mut wrt)?,
+        }
+        wrt.flush()
+    }
+
+    /// The fill colour for a status code: green for success, blue for redirects, orange for
+    /// client errors and red for server errors or pages which couldn't be fetched.
+    fn colour(status: Option<u16>) -> &'static str {
+        match status {
+            Some(200..=299) => "#a6d96a",
+            Some(300..=399) => "#92c5de",
+            Some(400..=499) => "#fdae61",
+            Some(500..=599) | None => "#d7191c",
+            _ => "#d9d9d9",
+        }
+    }
+
+    fn escape_dot(value: &str) -> String {
+        value.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GraphNode, SiteGraph};
+    use crate::{
+        results::{CrawlResults, PageResult},
+        types::{Link, LinkKind},
+    };
+    use reqwest::StatusCode;
+    use url::Url;
+
+    fn build_results() -> CrawlResults {
+        let seed = Url::parse("https://example.com/").unwrap();
+        let results = CrawlResults::new();
+        let add = |path: &str, status: StatusCode, links: &[&str]| {
+            let url = seed.join(path).unwrap();
+            let mut page = PageResult::new(url.clone(), url, status);
+            for link in links {
+                let link = seed.join(link).unwrap();
+                page.links.insert(Link::new(link, LinkKind::Navigation));
+            }
+            results.insert(page);
+        };
+        add("/", StatusCode::OK, &["/blog/a", "/blog/b", "/external"]);
+        add("/blog/a", StatusCode::OK, &["/blog/b", "/"]);
+        add("/blog/b", StatusCode::NOT_FOUND, &[]);
+        results
+    }
+
+    #[test]
+    fn node_per_page() {
+        let graph = SiteGraph::new(&build_results(), None);
+        assert_eq!(graph.nodes.len(), 3);
+        assert_eq!(
+            graph.links("https://example.com/blog/a", "https://example.com/blog/b"),
+            1
+        );
+        // Links to pages which weren't visited are left out.
+        assert_eq!(graph.edges.len(), 4);
+    }
+
+    #[test]
+    fn collapse_by_path_prefix() {
+        let graph = SiteGraph::new(&build_results(), Some(1));
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(
+            graph.node("https://example.com/blog/*"),
+            Some(&GraphNode {
+                pages: 2,
+                status: Some(404)
+            })
+        );
+        assert_eq!(
+            graph.links("https://example.com/", "https://example.com/blog/*"),
+            2
+        );
+        assert_eq!(
+            graph.links("https://example.com/blog/*", "https://example.com/"),
+            1
+        );
+    }
+
+    #[test]
+    fn write_dot() {
+        let mut output = Vec::new();
+        SiteGraph::new(&build_results(), Some(1))
+            .write_dot(&mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("digraph site {\n"));
+        assert!(output.contains(
+            r##""https://example.com/blog/*" [label="https://example.com/blog/*\n(2 pages)", fillcolor="#fdae61"];"##
+        ));
+        assert!(output
+            .contains(r#""https://example.com/" -> "https://example.com/blog/*" [label="2"];"#));
+    }
+
+    #[test]
+    fn write_graphml() {
+        let mut output = Vec::new();
+        SiteGraph::new(&build_results(), None)
+            .write_graphml(&mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output
+            .contains(r#"<node id="https://example.com/blog/b"><data key="status">404</data>"#));
+        assert!(output.contains(
+            r#"<edge source="https://example.com/" target="https://example.com/blog/a"><data key="links">1</data></edge>"#
+        ));
+        assert!(output.trim_end().ends_with("</graphml>"));
+    }
+}
@@ -0,0 +1,212 @@
+use crate::results::PageResult;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    io::{self, Write},
+};
+use url::Url;
+
+/// The visited pages arranged as a hierarchy, either by path segment or by the page each was
+/// first found on.
+pub struct SiteTree {
+    roots: Vec<TreeNode>,
+}
+
+/// A page, or a path segment with no page of its own, along with the nodes below it.
+#[derive(Debug, Default)]
+struct TreeNode {
+    label: String,
+    /// The page's status code, or `unreachable` if it couldn't be fetched. Unset for a path
+    /// segment with no page of its own.
+    status: Option<String>,
+    children: BTreeMap<String, TreeNode>,
+}
+
+impl TreeNode {
+    fn new(label: String) -> Self {
+        TreeNode {
+            label,
+            ..Default::default()
+        }
+    }
+}
+
+impl SiteTree {
+    /// Group the pages by path segment under a node per origin. For example `/blog/a` is
+    /// shown as `a` under `blog`, whether or not `/blog` was visited. The query is kept on the
+    /// last segment, or as a node of its own under the origin, e.g. `?page=2` for `/?page=2`.
+    pub fn by_path(pages: &[PageResult]) -> Self {
+        let mut origins: BTreeMap<String, TreeNode> = BTreeMap::new();
+        for page in pages {
+            let origin = format!("{}/", page.url.origin().ascii_serialization());
+            let mut node = origins
+                .entry(origin.clone())
+                .or_insert_with(|| TreeNode::new(origin));
+            let mut labels: Vec<String> = page
+                .url
+                .path_segments()
+                .map(|segments| {
+                    segments
+                        .filter(|segment| !segment.is_empty())
+                        .map(str::to_string)
+                        .collect()
+                })
+                .unwrap_or_default();
+            if let Some(query) = page.url.query() {
+                match labels.last_mut() {
+                    Some(last) => *last = format!("{}?{}", last, query),
+                    None => labels.push(format!("?{}", query)),
+                }
+            }
+            for label in labels {
+                node = node
+                    .children
+                    .entry(label.clone())
+                    .or_insert_with(|| TreeNode::new(label));
+            }
+            node.status = Some(Self::status(page));
+        }
+        SiteTree {
+            roots: origins.into_values().collect(),
+        }
+    }
+
+    /// Arrange the pages by the page each was first found on, starting from the seed URL.
+    pub fn by_discovery(pages: &[PageResult]) -> Self {
+        let visited: HashSet<&Url> = pages.iter().map(|page| &page.url).collect();
+        let mut children: HashMap<&Url, Vec<&PageResult>> = HashMap::new();
+        let mut roots = Vec::new();
+        for page in pages {
+            match page.referrer.as_ref().filter(|url| visited.contains(url)) {
+                Some(referrer) => children.entry(referrer).or_default().push(page),
+                None => roots.push(page),
+            }
+        }
+        roots.sort_by(|a, b| a.url.cmp(&b.url));
+
+        let mut seen = HashSet::new();
+        let roots = roots
+            .into_iter()
+            .map(|page| Self::discovery_node(page, &children, &mut seen))
+            .collect();
+        SiteTree { roots }
+    }
+
+    fn discovery_node<'a>(
+        page: &'a PageResult,
+        children: &HashMap<&Url, Vec<&'a PageResult>>,
+        seen: &mut HashSet<&'a Url>,
+    ) -> TreeNode {
+        let mut node = TreeNode::new(page.url.to_string());
+        node.status = Some(Self::status(page));
+        seen.insert(&page.url);
+        for child in children.get(&page.url).into_iter().flatten() {
+            if !seen.contains(&child.url) {
+                let child = Self::discovery_node(child, children, seen);
+                node.children.insert(child.label.clone(), child);
+            }
+        }
+        node
+    }
+
+    fn status(page: &PageResult) -> String {
+        match page.status {
+            Some(status) => status.as_u16().to_string(),
+            None => "unreachable".to_string(),
+        }
+    }
+
+    /// Write the tree with one node per line, showing its status code, if visited, and its
+    /// number of children.
+    pub fn write(&self, mut wrt: impl Write) -> io::Result<()> {
+        for root in &self.roots {
+            Self::write_node(&mut wrt, root, "", "")?;
+        }
+        Ok(())
+    }
+
+    fn write_node(
+        wrt: &mut impl Write,
+        node: &TreeNode,
+        prefix: &str,
+        child_prefix: &str,
+    ) -> io::Result<()> {
+        write!(wrt, "{}{}", prefix, node.label)?;
+        if let Some(status) = &node.status {
+            write!(wrt, " [{}]", status)?;
+        }
+        writeln!(wrt, " ({})", node.children.len())?;
+
+        let last = node.children.len().saturating_sub(1);
+        for (i, child) in node.children.values().enumerate() {
+            let (branch, indent) = if i == last {
+                ("└── ", "    ")
+            } else {
+                ("├── ", "│   ")
+            };
+            Self::write_node(
+                wrt,
+                child,
+                &format!("{}{}", child_prefix, branch),
+                &format!("{}{}", child_prefix, indent),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SiteTree;
+    use crate::results::PageResult;
+    use reqwest::StatusCode;
+    use url::Url;
+
+    fn pages() -> Vec<PageResult> {
+        let seed = Url::parse("https://example.com/").unwrap();
+        let page = |path: &str, status: StatusCode, referrer: Option<&str>| {
+            let url = seed.join(path).unwrap();
+            let mut page = PageResult::new(url.clone(), url, status);
+            page.referrer = referrer.map(|referrer| seed.join(referrer).unwrap());
+            page
+        };
+        vec![
+            page("/", StatusCode::OK, None),
+            page("/blog/b", StatusCode::NOT_FOUND, Some("/about")),
+            page("/about", StatusCode::OK, Some("/")),
+            page("/blog/a", StatusCode::OK, Some("/")),
+        ]
+    }
+
+    fn render(tree: SiteTree) -> String {
+        let mut output = Vec::new();
+        tree.write(&mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn by_path() {
+        let mut pages = pages();
+        let next = Url::parse("https://example.com/?page=2").unwrap();
+        pages.push(PageResult::new(next.clone(), next, StatusCode::OK));
+        assert_eq!(
+            render(SiteTree::by_path(&pages)),
+            "https://example.com/ [200] (3)\n\
+             ├── ?page=2 [200] (0)\n\
+             ├── about [200] (0)\n\
+             └── blog (2)\n    \
+                 ├── a [200] (0)\n    \
+                 └── b [404] (0)\n"
+        );
+    }
+
+    #[test]
+    fn by_discovery() {
+        assert_eq!(
+            render(SiteTree::by_discovery(&pages())),
+            "https://example.com/ [200] (2)\n\
+             ├── https://example.com/about [200] (1)\n\
+             │   └── https://example.com/blog/b [404] (0)\n\
+             └── https://example.com/blog/a [200] (0)\n"
+        );
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    config::Scope,
+    results::{CrawlResults, PageResult},
+};
+use html_escape::encode_text;
+use std::{
+    collections::BTreeSet,
+    io::{self, Write},
+};
+use url::Url;
+
+/// An XML sitemap listing the pages of a crawl which search engines should index.
+pub struct Sitemap {
+    urls: BTreeSet<Url>,
+}
+
+impl Sitemap {
+    /// List the HTML pages which were fetched successfully, at the URL they were served from.
+    /// Pages which asked not to be indexed, duplicate another page, or declare a different
+    /// canonical URL are left out, as are pages redirected to a host outside the scope of a
+    /// crawl from the seed URL.
+    pub fn new(results: &CrawlResults, seed: &Url, scope: Scope) -> Self {
+        let subdomain = seed.host_str().unwrap_or_default();
+        let urls = results
+            .pages()
+            .into_iter()
+            .filter(Self::indexable)
+            .map(|page| page.final_url)
+            .filter(|url| scope.includes_host(subdomain, url.host_str().unwrap_or_default()))
+            .collect();
+        Sitemap { urls }
+    }
+
+    fn indexable(page: &PageResult) -> bool {
+        let html = page
+            .content_type
+            .as_deref()
+            .is_none_or(|content_type| content_type.starts_with("text/html"));
+        let canonical = page
+            .canonical
+            .as_ref()
+            .is_none_or(|canonical| *canonical == page.final_url);
+        page.is_success()
+            && html
+            && canonical
+            && !page.robots.noindex
+            && page.duplicate_of.is_none()
+    }
+
+    pub fn urls(&self) -> &BTreeSet<Url> {
+        &self.urls
+    }
+
+    /// Write the sitemap in the sitemaps.org XML format.
+    pub fn write(&self, mut wrt: impl Write) -> io::Result<()> {
+        writeln!(wrt, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(
+            wrt,
+            r#"<urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9">"#
+        )?;
+        for url in &self.urls {
+            writeln!(wrt, "  <url><loc>{}</loc></url>", encode_text(url.as_str()))?;
+        }
+        writeln!(wrt, "</urlset>")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Sitemap;
+    use crate::{
+        config::Scope,
+        results::{CrawlResults, PageResult},
+    };
+    use reqwest::StatusCode;
+    use url::Url;
+
+    #[test]
+    fn indexable_pages() {
+        let seed = Url::parse("https://example.com/").unwrap();
+        let page = |path: &str, status: StatusCode| {
+            let url = seed.join(path).unwrap();
+            PageResult::new(url.clone(), url, status)
+        };
+        let results = CrawlResults::new();
+        results.insert(page("/", StatusCode::OK));
+        results.insert(page("/missing", StatusCode::NOT_FOUND));
+        let mut hidden = page("/hidden", StatusCode::OK);
+        hidden.robots.noindex = true;
+        results.insert(hidden);
+        let mut copy = page("/copy", StatusCode::OK);
+        copy.canonical = Some(seed.clone());
+        results.insert(copy);
+        let mut image = page("/logo.png", StatusCode::OK);
+        image.content_type = Some("image/png".to_string());
+        results.insert(image);
+        let mut search = page("/search?q=a&page=2", StatusCode::OK);
+        search.content_type = Some("text/html; charset=utf-8".to_string());
+        results.insert(search);
+        let other = Url::parse("https://other.example.org/").unwrap();
+        results.insert(PageResult::new(
+            seed.join("/elsewhere").unwrap(),
+            other,
+            StatusCode::OK,
+        ));
+
+        let mut output = Vec::new();
+        Sitemap::new(&results, &seed, Scope::Host)
+            .write(&mut output)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n  \
+             <url><loc>https://example.com/</loc></url>\n  \
+             <url><loc>https://example.com/search?q=a&amp;page=2</loc></url>\n\
+             </urlset>\n"
+        );
+    }
+}
@@ -1,42 +1,79 @@
-use std::collections::HashSet;
+use std::collections::HashMap;
 use std::hash::Hash;
 use std::sync::{Arc, Mutex};
 
-/// A thread-safe store which only allows items to be inserted.
+/// A store of visited items which only allows items to be inserted. Each item may carry a
+/// value, recorded when the item is first inserted. Implementations must be thread-safe.
+pub trait VisitedStore<T, V = ()>: Send + Sync {
+    /// Add an item with its value to the store. If the item was already present, the value
+    /// recorded when it was first inserted is returned and the store is left unchanged.
+    fn insert_or_get(&self, item: T, value: V) -> Option<V>;
+
+    /// Add a value to the store.
+    fn insert(&self, item: T) -> bool
+    where
+        V: Default,
+    {
+        self.insert_or_get(item, V::default()).is_none()
+    }
+}
+
+impl<T, V, S: VisitedStore<T, V> + ?Sized> VisitedStore<T, V> for Box<S> {
+    fn insert_or_get(&self, item: T, value: V) -> Option<V> {
+        (**self).insert_or_get(item, value)
+    }
+}
+
+/// A thread-safe in-memory store.
 #[derive(Default)]
-pub struct Store<T: Eq + Hash> {
-    store: Arc<Mutex<HashSet<T>>>,
+pub struct Store<T: Eq + Hash, V = ()> {
+    store: Arc<Mutex<HashMap<T, V>>>,
 }
 
-impl<T: Eq + Hash> Store<T> {
+impl<T: Eq + Hash, V> Store<T, V> {
     pub fn new() -> Self {
         Self {
-            store: Arc::new(Mutex::new(HashSet::new())),
+            store: Arc::new(Mutex::new(HashMap::new())),
         }
     }
+}
 
-    /// Add a value to the store.
-    pub fn insert(&self, item: T) -> bool {
+impl<T: Eq + Hash + Send, V: Clone + Send> VisitedStore<T, V> for Store<T, V> {
+    fn insert_or_get(&self, item: T, value: V) -> Option<V> {
         let mut store = match self.store.lock().ok() {
             Some(store) => store,
             None => {
                 eprintln!("DataStore lock poisioned");
-                return false;
+                // Report the item as already seen so it isn't processed again.
+                return Some(value);
             }
         };
-        store.insert(item)
+        match store.get(&item) {
+            Some(existing) => Some(existing.clone()),
+            None => {
+                store.insert(item, value);
+                None
+            }
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Store;
+    use super::{Store, VisitedStore};
 
     #[test]
     fn insert() {
-        let store = Store::new();
+        let store: Store<&str> = Store::new();
         assert!(store.insert("a"));
         assert!(store.insert("b"));
         assert!(!store.insert("a"));
     }
+
+    #[test]
+    fn insert_or_get() {
+        let store = Store::new();
+        assert_eq!(store.insert_or_get("a", 1), None);
+        assert_eq!(store.insert_or_get("a", 2), Some(1));
+    }
 }
@@ -1,3 +1,9 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Ordering,
+    hash::{Hash, Hasher},
+    path::PathBuf,
+};
 use thiserror::Error;
 use url::{ParseError, Url};
 
@@ -11,4 +17,169 @@ pub enum CrawlerError {
     SubdomainError { url: Url },
     #[error("please specify a single URL argument")]
     InputMalformed,
+    #[error("Unable to open the store at {path:?}")]
+    StoreUnavailable { path: PathBuf },
+    #[error("Unable to read or write the checkpoint at {path:?}")]
+    CheckpointError { path: PathBuf },
+    #[error("Unable to read or write the crawl results at {path:?}")]
+    ResultsError { path: PathBuf },
+    #[error("Unable to read the cookies at {path:?}")]
+    CookiesError { path: PathBuf },
+    #[error("Unable to read the netrc file at {path:?}")]
+    NetrcError { path: PathBuf },
+    #[error("Unable to read the CA certificates at {path:?}")]
+    CaBundleError { path: PathBuf },
+    #[error("Unable to log in: {0}")]
+    LoginError(String),
+    #[error("Unable to write the output: {0}")]
+    OutputError(String),
+    #[error("Unable to create the HTTP client: {0}")]
+    ClientError(String),
+    #[error("Invalid config file {path:?}: {message}")]
+    ConfigError { path: PathBuf, message: String },
+}
+
+/// The kind of element a link was extracted from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkKind {
+    /// Pages the user can navigate to, e.g. `<a href>` or a meta refresh.
+    Navigation,
+    /// Resources loaded by the page, e.g. images, scripts and stylesheets.
+    Asset,
+    /// Documents embedded in the page, e.g. `<iframe src>`.
+    Embed,
+    /// Form submission targets.
+    Form,
+}
+
+/// A link found on a page, tagged with the kind of element it came from.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Link {
+    pub url: Url,
+    pub kind: LinkKind,
+    /// The element was marked `rel="nofollow"`.
+    pub nofollow: bool,
+    /// The text of the element, or its `alt` text. Links which only differ by their text are
+    /// considered equal, keeping the text of whichever was found first.
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+impl Link {
+    pub fn new(url: Url, kind: LinkKind) -> Self {
+        Link {
+            url,
+            kind,
+            nofollow: false,
+            text: None,
+        }
+    }
+
+    /// Mark whether the link should not be followed.
+    pub fn with_nofollow(mut self, nofollow: bool) -> Self {
+        self.nofollow = nofollow;
+        self
+    }
+
+    /// Set the anchor text of the link.
+    pub fn with_text(mut self, text: Option<String>) -> Self {
+        self.text = text;
+        self
+    }
+}
+
+impl PartialEq for Link {
+    fn eq(&self, other: &Self) -> bool {
+        (&self.url, self.kind, self.nofollow) == (&other.url, other.kind, other.nofollow)
+    }
+}
+
+impl Eq for Link {}
+
+impl Hash for Link {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (&self.url, self.kind, self.nofollow).hash(state);
+    }
+}
+
+/// Links are ordered by URL, then kind, then whether they are nofollow, so that links to the
+/// same URL are always listed in the same order.
+impl Ord for Link {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (&self.url, self.kind, self.nofollow).cmp(&(&other.url, other.kind, other.nofollow))
+    }
+}
+
+impl PartialOrd for Link {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A URL waiting to be visited, along with how it was discovered.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ScheduledUrl {
+    pub url: Url,
+    /// The number of links followed from the seed URL.
+    pub depth: usize,
+    /// The page the URL was linked from.
+    pub referrer: Option<Url>,
+}
+
+impl ScheduledUrl {
+    /// The URL a crawl starts from.
+    pub fn seed(url: Url) -> Self {
+        ScheduledUrl {
+            url,
+            depth: 0,
+            referrer: None,
+        }
+    }
+
+    /// A URL linked to from this one.
+    pub fn link(&self, url: Url) -> Self {
+        ScheduledUrl {
+            url,
+            depth: self.depth + 1,
+            referrer: Some(self.url.clone()),
+        }
+    }
+}
+
+/// Page level robots directives, from `<meta name="robots">` or the `X-Robots-Tag` header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RobotsDirectives {
+    /// The page should not be reported.
+    pub noindex: bool,
+    /// Links on the page should not be followed.
+    pub nofollow: bool,
+}
+
+impl RobotsDirectives {
+    /// Parse a comma separated list of directives, e.g. `noindex, nofollow`. Directives
+    /// addressed to a specific user agent, e.g. `googlebot: noindex`, are ignored.
+    pub fn parse(value: &str) -> Self {
+        let mut directives = RobotsDirectives::default();
+        for directive in value.split(',').map(|d| d.trim().to_ascii_lowercase()) {
+            match directive.as_str() {
+                "noindex" => directives.noindex = true,
+                "nofollow" => directives.nofollow = true,
+                "none" => {
+                    directives.noindex = true;
+                    directives.nofollow = true;
+                }
+                _ => {}
+            }
+        }
+        directives
+    }
+
+    /// Combine two sets of directives, the most restrictive wins.
+    pub fn merge(self, other: RobotsDirectives) -> Self {
+        RobotsDirectives {
+            noindex: self.noindex || other.noindex,
+            nofollow: self.nofollow || other.nofollow,
+        }
+    }
 }
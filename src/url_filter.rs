@@ -1,32 +1,109 @@
-use crate::store::Store;
+use crate::{
+    config::{host_entry, HostConfig, Scope},
+    sharded_store::ShardedStore,
+    store::VisitedStore,
+    types::{Link, LinkKind},
+};
 use robotstxt::DefaultMatcher;
-use std::collections::HashSet;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::RwLock,
+};
 use url::Url;
 
 pub struct UrlFilter {
     subdomain: String,
-    url_store: Store<Url>,
-    robots_txt: String,
+    scope: Scope,
+    /// The directory of the seed URL, for `Scope::Prefix`.
+    path_prefix: String,
+    url_store: Box<dyn VisitedStore<Url>>,
+    /// The robots.txt of each host, which only applies to URLs on that host.
+    robots_txt: RwLock<HashMap<String, String>>,
+    /// The agent matched against robots.txt rules.
+    agent: String,
+    follow: HashSet<LinkKind>,
+    /// The paths followed on particular hosts.
+    hosts: BTreeMap<String, HostConfig>,
+    /// Skip links which look like they log out, so that a logged in session is kept.
+    avoid_logout: bool,
 }
 
 impl UrlFilter {
+    /// Create a filter which follows navigation links only, with the robots.txt of the
+    /// subdomain.
     pub fn new(subdomain: String, robots_txt: String) -> Self {
         UrlFilter {
+            robots_txt: RwLock::new(HashMap::from([(subdomain.clone(), robots_txt)])),
             subdomain,
-            url_store: Store::new(),
-            robots_txt,
+            scope: Scope::Host,
+            path_prefix: "/".to_string(),
+            url_store: Box::new(ShardedStore::default()),
+            agent: "*".to_string(),
+            follow: HashSet::from([LinkKind::Navigation]),
+            hosts: BTreeMap::new(),
+            avoid_logout: false,
         }
     }
 
-    /// Filter a set of URLs based on the following criteria
-    /// 1. Be in the same subdomain.
-    /// 2. Are allowed by robots.txt.
-    /// 3. Have not been visited before.
-    pub fn filter(&self, urls: HashSet<Url>) -> HashSet<Url> {
-        let filtered: Vec<Url> = urls
+    /// Follow links in the scope, relative to the seed URL. Only links to the subdomain are
+    /// followed by default.
+    pub fn with_scope(mut self, scope: Scope, seed: &Url) -> Self {
+        self.scope = scope;
+        let path = seed.path();
+        self.path_prefix = path[..=path.rfind('/').unwrap_or(0)].to_string();
+        self
+    }
+
+    /// Match robots.txt rules for the product name at the start of the user agent, e.g.
+    /// `ExampleBot` for `ExampleBot/1.0`, rather than only the rules for every agent.
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        let product = user_agent.split(['/', ' ']).next().unwrap_or_default();
+        if !product.is_empty() {
+            self.agent = product.to_string();
+        }
+        self
+    }
+
+    /// Keep the URLs which have been visited in the given store.
+    pub fn with_store(mut self, url_store: impl VisitedStore<Url> + 'static) -> Self {
+        self.url_store = Box::new(url_store);
+        self
+    }
+
+    /// Set the kinds of link which should be followed. Links of any other kind are reported
+    /// on the page they were found but never scheduled.
+    pub fn with_follow(mut self, kinds: HashSet<LinkKind>) -> Self {
+        self.follow = kinds;
+        self
+    }
+
+    /// Only follow links to each host which are allowed by its `include` and `exclude` paths.
+    pub fn with_hosts(mut self, hosts: &BTreeMap<String, HostConfig>) -> Self {
+        self.hosts = hosts.clone();
+        self
+    }
+
+    /// Skip links whose URL or text looks like it logs out, such as `/logout` or "Sign out".
+    pub fn with_logout_avoided(mut self) -> Self {
+        self.avoid_logout = true;
+        self
+    }
+
+    /// Filter a set of links based on the following criteria
+    /// 1. Be a kind of link which is followed.
+    /// 2. Be in scope, by default the same subdomain, and allowed by the host's paths.
+    /// 3. Are allowed by robots.txt.
+    /// 4. Have not been visited before.
+    pub fn filter(&self, links: HashSet<Link>) -> HashSet<Url> {
+        let filtered: Vec<Url> = links
             .into_iter()
-            // Exclude URLs which do not match the subdomain.
-            .filter(|url| url.host_str().unwrap_or("") == self.subdomain)
+            // Exclude kinds of link which are not followed.
+            .filter(|link| self.follow.contains(&link.kind))
+            // Exclude links which would end a logged in session.
+            .filter(|link| !(self.avoid_logout && Self::is_logout(link)))
+            .map(|link| link.url)
+            // Exclude URLs which are out of scope.
+            .filter(|url| self.in_scope(url))
             // Exclude URLs which are not allowed by robots.txt
             .filter(|url| self.allowed(url))
             .collect();
@@ -44,20 +121,92 @@ impl UrlFilter {
             .collect()
     }
 
-    /// Determine whether the URL is allowed by robots.txt
-    fn allowed(&self, url: &Url) -> bool {
+    /// Use the robots.txt of another host for URLs on that host.
+    pub fn add_robots_txt(&self, host: &str, robots_txt: String) {
+        if let Ok(mut files) = self.robots_txt.write() {
+            files.insert(host.to_string(), robots_txt);
+        }
+    }
+
+    /// Determine whether the URL is in scope on a host whose robots.txt hasn't been added, so
+    /// it must be fetched before links to the host are filtered.
+    pub fn needs_robots_txt(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or_default();
+        let known = match self.robots_txt.read() {
+            Ok(files) => files.contains_key(host),
+            Err(_) => true,
+        };
+        !known && self.in_scope(url)
+    }
+
+    /// Determine whether the URL is within the scope of the crawl.
+    fn in_scope(&self, url: &Url) -> bool {
+        if let Some(config) = host_entry(&self.hosts, url) {
+            if !config.allows_path(url.path()) {
+                return false;
+            }
+        }
+        let prefix = match self.scope {
+            Scope::Prefix => self.path_prefix.as_str(),
+            Scope::Host | Scope::Domain => "/",
+        };
+        !self.is_external(url) && url.path().starts_with(prefix)
+    }
+
+    /// Determine whether the URL is on a host the crawl doesn't visit, e.g. another site.
+    pub fn is_external(&self, url: &Url) -> bool {
+        let host = url.host_str().unwrap_or("");
+        !self.scope.includes_host(&self.subdomain, host)
+    }
+
+    /// Determine whether the link looks like it logs out, from its path, query or text. For
+    /// example `/logout`, `/sign-out`, `?action=logoff` or "Log out". Words are matched whole,
+    /// or split across two adjacent words, so `/blog/outages` is not a logout link.
+    pub fn is_logout(link: &Link) -> bool {
+        let matches = |value: &str| {
+            let words: Vec<String> = value
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|word| !word.is_empty())
+                .map(str::to_lowercase)
+                .collect();
+            let pairs = words.windows(2).map(|pair| pair.concat());
+            words
+                .iter()
+                .cloned()
+                .chain(pairs)
+                .any(|word| LOGOUT_WORDS.contains(&word.as_str()))
+        };
+        matches(link.url.path())
+            || link.url.query().is_some_and(matches)
+            || link.text.as_deref().is_some_and(matches)
+    }
+
+    /// Determine whether the URL is allowed by the robots.txt of its host. Everything is
+    /// allowed on hosts without one.
+    pub fn allowed(&self, url: &Url) -> bool {
+        let Ok(files) = self.robots_txt.read() else {
+            return true;
+        };
+        let Some(robots_txt) = files.get(url.host_str().unwrap_or_default()) else {
+            return true;
+        };
         let mut matcher = DefaultMatcher::default();
-        matcher.one_agent_allowed_by_robots(&self.robots_txt, "*", url.as_ref())
+        matcher.one_agent_allowed_by_robots(robots_txt, &self.agent, url.as_ref())
     }
 }
 
+/// Words which mark a logout link, on their own or split in two, e.g. `log-out`.
+const LOGOUT_WORDS: &[&str] = &["logout", "logoff", "signout", "signoff"];
+
 #[cfg(test)]
 mod tests {
+    use crate::config::{HostConfig, Scope};
+    use crate::types::{Link, LinkKind};
     use crate::url_filter::UrlFilter;
-    use std::collections::HashSet;
+    use std::collections::{BTreeMap, HashSet};
     use url::Url;
 
-    fn build_urls() -> HashSet<Url> {
+    fn build_urls() -> HashSet<Link> {
         // Dataset contains 21 links with a matching subdomain.
         let links = vec![
             "https://we83.adj.st/home?adj_t=1dj2rkno_1dxkjz95&adj_redirect=https%3A%2F%2Fexample.com%2Fsign-up&adj_engagement_type=fallback_click",
@@ -97,7 +246,7 @@ mod tests {
         ];
         links
             .iter()
-            .map(|input| Url::parse(input).unwrap())
+            .map(|input| Link::new(Url::parse(input).unwrap(), LinkKind::Navigation))
             .collect()
     }
 
@@ -132,12 +281,35 @@ mod tests {
 
         // Add a new URL to the dataset and filter again, only this URL should be returned.
         let new_url = Url::parse("https://example.com/gonzo").unwrap();
-        urls.insert(new_url.clone());
+        urls.insert(Link::new(new_url.clone(), LinkKind::Navigation));
         let filtered = url_filter.filter(urls);
         assert_eq!(filtered.len(), 1);
         assert_eq!(filtered.into_iter().next().unwrap(), new_url);
     }
 
+    #[test]
+    fn filter_link_kinds() {
+        let start_url = Url::parse("https://example.com/").unwrap();
+        let subdomain = start_url.host_str().unwrap().to_string();
+        let page = Link::new(start_url.join("page").unwrap(), LinkKind::Navigation);
+        let image = Link::new(start_url.join("image.png").unwrap(), LinkKind::Asset);
+        let links = HashSet::from([page.clone(), image.clone()]);
+
+        // Only navigation links are followed by default.
+        let url_filter = UrlFilter::new(subdomain.clone(), build_robots_txt());
+        assert_eq!(
+            url_filter.filter(links.clone()),
+            HashSet::from([page.url.clone()])
+        );
+
+        let url_filter = UrlFilter::new(subdomain, build_robots_txt())
+            .with_follow(HashSet::from([LinkKind::Navigation, LinkKind::Asset]));
+        assert_eq!(
+            url_filter.filter(links),
+            HashSet::from([page.url, image.url])
+        );
+    }
+
     #[test]
     fn apply_robots_txt() {
         let start_url = Url::parse("https://example.com/").unwrap();
@@ -158,4 +330,159 @@ mod tests {
         assert!(!url_filter.allowed(&disallow_2));
         assert!(!url_filter.allowed(&disallow_3));
     }
+
+    #[test]
+    fn apply_robots_txt_per_host() {
+        let seed = Url::parse("https://www.example.com/").unwrap();
+        let url_filter = UrlFilter::new(
+            "www.example.com".to_string(),
+            "User-agent: *\nDisallow: /private/\n".to_string(),
+        )
+        .with_scope(Scope::Domain, &seed);
+        let api = Url::parse("https://api.example.com/private/keys").unwrap();
+        let www = Url::parse("https://www.example.com/private/keys").unwrap();
+
+        // Links to another host in scope wait for its robots.txt.
+        assert!(url_filter.needs_robots_txt(&api));
+        assert!(!url_filter.needs_robots_txt(&www));
+        assert!(!url_filter.needs_robots_txt(&Url::parse("https://other.com/").unwrap()));
+
+        url_filter.add_robots_txt("api.example.com", "User-agent: *\nDisallow: /v1/\n".into());
+        assert!(!url_filter.needs_robots_txt(&api));
+        assert!(url_filter.allowed(&api));
+        assert!(!url_filter.allowed(&api.join("/v1/users").unwrap()));
+        assert!(!url_filter.allowed(&www));
+    }
+
+    #[test]
+    fn filter_by_scope() {
+        let seed = Url::parse("https://www.example.com/docs/index.html").unwrap();
+        let subdomain = seed.host_str().unwrap().to_string();
+        let urls = [
+            "https://www.example.com/docs/intro",
+            "https://www.example.com/blog",
+            "https://api.example.com/",
+            "https://example.com.evil.com/",
+        ];
+        let links: HashSet<_> = urls
+            .iter()
+            .map(|url| Link::new(Url::parse(url).unwrap(), LinkKind::Navigation))
+            .collect();
+        let filter = |scope| {
+            let url_filter =
+                UrlFilter::new(subdomain.clone(), String::new()).with_scope(scope, &seed);
+            let mut filtered: Vec<_> = url_filter
+                .filter(links.clone())
+                .into_iter()
+                .map(String::from)
+                .collect();
+            filtered.sort();
+            filtered
+        };
+
+        assert_eq!(
+            filter(Scope::Host),
+            vec![
+                "https://www.example.com/blog",
+                "https://www.example.com/docs/intro"
+            ]
+        );
+        assert_eq!(
+            filter(Scope::Domain),
+            vec![
+                "https://api.example.com/",
+                "https://www.example.com/blog",
+                "https://www.example.com/docs/intro"
+            ]
+        );
+        assert_eq!(
+            filter(Scope::Prefix),
+            vec!["https://www.example.com/docs/intro"]
+        );
+    }
+
+    #[test]
+    fn filter_by_host_paths() {
+        let host = HostConfig {
+            include: vec!["/docs/".to_string(), "/blog/".to_string()],
+            exclude: vec!["/docs/archive/".to_string()],
+            ..Default::default()
+        };
+        let hosts = BTreeMap::from([("example.com".to_string(), host)]);
+        let url_filter =
+            UrlFilter::new("example.com".to_string(), String::new()).with_hosts(&hosts);
+        let links: HashSet<_> = [
+            "https://example.com/docs/intro",
+            "https://example.com/docs/archive/old",
+            "https://example.com/blog/post",
+            "https://example.com/about",
+        ]
+        .iter()
+        .map(|url| Link::new(Url::parse(url).unwrap(), LinkKind::Navigation))
+        .collect();
+        let mut filtered: Vec<_> = url_filter
+            .filter(links)
+            .into_iter()
+            .map(String::from)
+            .collect();
+        filtered.sort();
+
+        assert_eq!(
+            filtered,
+            vec![
+                "https://example.com/blog/post",
+                "https://example.com/docs/intro"
+            ]
+        );
+    }
+
+    #[test]
+    fn avoid_logout_links() {
+        let link = |url: &str, text: &str| {
+            Link::new(Url::parse(url).unwrap(), LinkKind::Navigation)
+                .with_text(Some(text.to_string()))
+        };
+        let links = HashSet::from([
+            link("https://example.com/account", "Account"),
+            link("https://example.com/users/sign_out", "Bye"),
+            link("https://example.com/session?action=LogOff", "Leave"),
+            link("https://example.com/session/end", "Log out"),
+            link("https://example.com/blog/logging-output", "Logging output"),
+            link("https://example.com/blog/outages", "Outages"),
+            link("https://example.com/catalog/outlet", "Outlet"),
+            link("https://example.com/account?next=/log-out", "Settings"),
+        ]);
+        let url_filter = UrlFilter::new("example.com".to_string(), String::new());
+        assert_eq!(url_filter.filter(links.clone()).len(), 8);
+
+        let url_filter =
+            UrlFilter::new("example.com".to_string(), String::new()).with_logout_avoided();
+        let mut filtered: Vec<_> = url_filter
+            .filter(links)
+            .into_iter()
+            .map(String::from)
+            .collect();
+        filtered.sort();
+        assert_eq!(
+            filtered,
+            vec![
+                "https://example.com/account",
+                "https://example.com/blog/logging-output",
+                "https://example.com/blog/outages",
+                "https://example.com/catalog/outlet"
+            ]
+        );
+    }
+
+    #[test]
+    fn apply_robots_txt_for_user_agent() {
+        let robots_txt =
+            "User-agent: *\nDisallow: /private/\n\nUser-agent: ExampleBot\nDisallow: /\n";
+        let url = Url::parse("https://example.com/page").unwrap();
+        let url_filter = UrlFilter::new("example.com".to_string(), robots_txt.to_string());
+        assert!(url_filter.allowed(&url));
+
+        let url_filter = url_filter.with_user_agent("ExampleBot/1.0 (+https://example.com/bot)");
+        assert!(!url_filter.allowed(&url));
+    }
 }
@@ -1,8 +1,19 @@
 #[cfg(test)]
 mod tests {
+    use reqwest::StatusCode;
+    use std::{collections::BTreeMap, time::Duration};
     use url::Url;
-    use web_crawler_rs::crawler::Crawler;
-    use wiremock::matchers::{method, path};
+    use web_crawler_rs::{
+        audit::AuditIssue,
+        checkpoint::Checkpoint,
+        config::{CheckpointConfig, Config, LoginConfig, StoreBackend},
+        crawler::Crawler,
+        output::MemorySink,
+        results::{CrawlResults, PageResult},
+        shutdown::Shutdown,
+        types::{CrawlerError, ScheduledUrl},
+    };
+    use wiremock::matchers::{body_string, header, header_exists, header_regex, method, path};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     async fn build_site(mock_server: &MockServer) {
@@ -18,7 +29,7 @@ mod tests {
                         Disallow: /blog/authors/
                         Disallow: /-deeplinks/"#,
             ))
-            .mount(&mock_server)
+            .mount(mock_server)
             .await;
 
         // Mock the landing page 200 response with links
@@ -37,7 +48,7 @@ mod tests {
                 "#,
                 mock_server.uri()
             )))
-            .mount(&mock_server)
+            .mount(mock_server)
             .await;
 
         // Mock /a 200 response with links
@@ -56,7 +67,7 @@ mod tests {
                 "#,
                 mock_server.uri()
             )))
-            .mount(&mock_server)
+            .mount(mock_server)
             .await;
 
         // Mock /b 200 response with no links
@@ -71,7 +82,7 @@ mod tests {
                     </html>
                 "#,
             ))
-            .mount(&mock_server)
+            .mount(mock_server)
             .await;
 
         // Mock /f 404 response
@@ -86,7 +97,7 @@ mod tests {
                     </html>
                 "#,
             ))
-            .mount(&mock_server)
+            .mount(mock_server)
             .await;
     }
 
@@ -102,4 +113,569 @@ mod tests {
 
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn report_unreachable_seed() {
+        // Nothing listens on the port once the listener is dropped.
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let seed = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        drop(listener);
+
+        let result = Crawler::run_with_sink(seed, Config::default(), MemorySink::new()).await;
+        assert!(matches!(result, Err(CrawlerError::ClientError(_))));
+    }
+
+    #[tokio::test]
+    async fn records_depth_and_referrer() {
+        let mock_server = MockServer::start().await;
+        build_site(&mock_server).await;
+
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        let results = Crawler::run(seed.clone()).await.unwrap();
+
+        let root = results.get(&seed).unwrap();
+        assert_eq!((root.depth, root.referrer), (0, None));
+        let a = results.get(&seed.join("a").unwrap()).unwrap();
+        assert_eq!((a.depth, a.referrer.clone()), (1, Some(seed.clone())));
+        assert!(a.size > 0);
+        let e = results.get(&seed.join("e").unwrap()).unwrap();
+        assert_eq!((e.depth, e.referrer), (2, Some(a.url)));
+    }
+
+    #[tokio::test]
+    async fn write_to_sink() {
+        let mock_server = MockServer::start().await;
+        build_site(&mock_server).await;
+
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        let sink = MemorySink::new();
+        let results = Crawler::run_with_sink(seed, Config::default(), sink.clone())
+            .await
+            .unwrap();
+
+        let pages = sink.pages();
+        assert_eq!(pages.len(), results.len());
+        assert!(pages.iter().all(|page| results.get(&page.url).is_some()));
+    }
+
+    #[tokio::test]
+    async fn limit_depth_and_concurrency() {
+        let mock_server = MockServer::start().await;
+        build_site(&mock_server).await;
+
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        let config = Config {
+            max_depth: Some(1),
+            concurrency: Some(1),
+            ..Default::default()
+        };
+        let results = Crawler::run_with_config(seed, config).await.unwrap();
+
+        // The seed URL and the four pages it links to, but none of the links on those pages.
+        assert_eq!(results.len(), 5);
+        assert!(results.pages().iter().all(|page| page.depth <= 1));
+    }
+
+    #[tokio::test]
+    async fn end_to_end_disk_store() {
+        let mock_server = MockServer::start().await;
+        build_site(&mock_server).await;
+        let store_dir = tempfile::tempdir().unwrap();
+
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        let config = Config {
+            store: StoreBackend::Disk(store_dir.path().to_path_buf()),
+            ..Default::default()
+        };
+        let results = Crawler::run_with_config(seed.clone(), config.clone())
+            .await
+            .unwrap();
+
+        // Every page linked to is visited once. robots.txt only disallows /docs/ and
+        // /referral/, not /docs and /referral.
+        assert_eq!(results.len(), 8);
+
+        // A second crawl into the same directory starts afresh.
+        let results = Crawler::run_with_config(seed, config).await.unwrap();
+        assert_eq!(results.len(), 8);
+    }
+
+    #[tokio::test]
+    async fn resume_from_checkpoint() {
+        let mock_server = MockServer::start().await;
+        build_site(&mock_server).await;
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        let [a, b, c, docs] = ["a", "b", "c", "docs"].map(|p| seed.join(p).unwrap());
+
+        // The landing page was visited and /a is still to be visited.
+        let results = CrawlResults::new();
+        results.insert(PageResult::new(seed.clone(), seed.clone(), StatusCode::OK));
+        let frontier = vec![ScheduledUrl::seed(seed.clone()).link(a)];
+        let mut checkpoint = Checkpoint::new(seed.clone(), frontier, &results);
+        checkpoint.visited.extend([b, c, docs]);
+        let checkpoint_dir = tempfile::tempdir().unwrap();
+        checkpoint.save(checkpoint_dir.path()).unwrap();
+
+        let results = Crawler::resume(checkpoint_dir.path().to_path_buf(), Config::default())
+            .await
+            .unwrap();
+
+        // /a, and the new links /e, /f and /referral from it, are visited.
+        assert_eq!(results.len(), 5);
+        let requests = mock_server.received_requests().await.unwrap();
+        assert!(!requests.iter().any(|request| request.url.path() == "/"));
+
+        // The final checkpoint has nothing left to visit.
+        let checkpoint = Checkpoint::load(checkpoint_dir.path()).unwrap();
+        assert!(checkpoint.frontier.is_empty());
+        assert_eq!(checkpoint.pages.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn shutdown_saves_frontier() {
+        let mock_server = MockServer::start().await;
+        build_site(&mock_server).await;
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        let checkpoint_dir = tempfile::tempdir().unwrap();
+
+        // Shut down before the crawl takes the seed URL off the queue.
+        let shutdown = Shutdown::default();
+        shutdown.trigger();
+        let config = Config {
+            checkpoint: Some(CheckpointConfig::new(checkpoint_dir.path().to_path_buf())),
+            shutdown,
+            ..Default::default()
+        };
+        let results = Crawler::run_with_config(seed.clone(), config)
+            .await
+            .unwrap();
+
+        assert!(results.is_empty());
+        let checkpoint = Checkpoint::load(checkpoint_dir.path()).unwrap();
+        assert_eq!(checkpoint.frontier, vec![ScheduledUrl::seed(seed)]);
+    }
+
+    async fn mount_page(mock_server: &MockServer, page: &str, body: String) {
+        Mock::given(method("GET"))
+            .and(path(page))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn return_summary_and_audit_issues() {
+        let mock_server = MockServer::start().await;
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        let landing = format!(r#"<link rel="canonical" href="{}missing">"#, seed);
+        mount_page(&mock_server, "/", landing).await;
+
+        let sink = MemorySink::new();
+        let config = Config {
+            audit: true,
+            ..Default::default()
+        };
+        let results = Crawler::run_with_sink(seed.clone(), config, sink.clone())
+            .await
+            .unwrap();
+
+        // The issues are returned rather than written among the pages.
+        assert_eq!(sink.pages().len(), 1);
+        let summary = results.summary();
+        assert_eq!((summary.unvisited, summary.interrupted), (0, false));
+        assert_eq!(
+            summary.audit_issues,
+            vec![AuditIssue::CanonicalNotOk {
+                page: seed.clone(),
+                canonical: seed.join("missing").unwrap(),
+                status: StatusCode::NOT_FOUND,
+            }]
+        );
+    }
+
+    #[tokio::test]
+    async fn check_external_links() {
+        let mock_server = MockServer::start().await;
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        // Addressed as "localhost", another host than the seed, so it is checked but not crawled.
+        let external = MockServer::start().await;
+        let external_uri = external.uri().replace("127.0.0.1", "localhost");
+        let unreachable = "http://localhost:1/down";
+        let landing = format!(
+            r#"<a href="{0}/ok">ok</a><a href="{0}/gone">gone</a><a href="{0}/get-only">get</a><a href="{1}">down</a>"#,
+            external_uri, unreachable
+        );
+        mount_page(&mock_server, "/", landing).await;
+        Mock::given(method("HEAD"))
+            .and(path("/ok"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&external)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/gone"))
+            .respond_with(ResponseTemplate::new(410))
+            .mount(&external)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/get-only"))
+            .respond_with(ResponseTemplate::new(405))
+            .mount(&external)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/get-only"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&external)
+            .await;
+
+        let sink = MemorySink::new();
+        let config = Config {
+            check_external_links: true,
+            ..Default::default()
+        };
+        let results = Crawler::run_with_sink(seed.clone(), config, sink.clone())
+            .await
+            .unwrap();
+
+        // External targets are checked but not crawled.
+        assert_eq!(sink.pages().len(), 1);
+        let external_url = |path: &str| Url::parse(&format!("{}{}", external_uri, path)).unwrap();
+        let broken = results.broken_links();
+        assert_eq!(broken.len(), 2);
+        assert_eq!(
+            broken[&external_url("/gone")],
+            (Some(StatusCode::GONE), [seed.clone()].into())
+        );
+        assert_eq!(
+            broken[&Url::parse(unreachable).unwrap()],
+            (None, [seed].into())
+        );
+        assert_eq!(
+            results.summary().external_links[&external_url("/get-only")],
+            Some(StatusCode::OK)
+        );
+    }
+
+    #[tokio::test]
+    async fn report_pages_which_time_out() {
+        let mock_server = MockServer::start().await;
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        mount_page(&mock_server, "/", r#"<a href="/slow">slow</a>"#.to_string()).await;
+        Mock::given(method("GET"))
+            .and(path("/slow"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&mock_server)
+            .await;
+
+        let mut config = Config::default();
+        config.client.timeout = Some(Duration::from_millis(200));
+        let sink = MemorySink::new();
+        let results = Crawler::run_with_sink(seed.clone(), config, sink.clone())
+            .await
+            .unwrap();
+
+        // The page is recorded along with why it failed, and reported as broken.
+        let slow = seed.join("slow").unwrap();
+        let page = results.get(&slow).unwrap();
+        assert_eq!(page.status, None);
+        assert_eq!(page.error.as_deref(), Some("timed out"));
+        assert_eq!(sink.pages().len(), 2);
+        assert_eq!(results.broken_links()[&slow], (None, [seed].into()));
+    }
+
+    #[tokio::test]
+    async fn report_export_failures() {
+        let mock_server = MockServer::start().await;
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        mount_page(&mock_server, "/", "<p>Home</p>".to_string()).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        let config = Config {
+            export_edges: Some(missing.join("edges.csv")),
+            export_graph: Some(missing.join("graph.dot")),
+            export_nodes: Some(dir.path().join("nodes.csv")),
+            ..Default::default()
+        };
+        let result = Crawler::run_with_sink(seed, config, MemorySink::new()).await;
+
+        // Each file which couldn't be written is reported, while the others are still written.
+        let Err(CrawlerError::OutputError(message)) = result else {
+            panic!("expected an output error");
+        };
+        assert!(message.contains("edges.csv"), "{}", message);
+        assert!(message.contains("graph.dot"), "{}", message);
+        assert!(dir.path().join("nodes.csv").exists());
+    }
+
+    #[tokio::test]
+    async fn keep_headers_in_scope() {
+        let mock_server = MockServer::start().await;
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        let external = MockServer::start().await;
+        let external_uri = external.uri().replace("127.0.0.1", "localhost");
+        let landing = format!(r#"<a href="{}/page">external</a>"#, external_uri);
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .and(header("Authorization", "Bearer abc"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(landing))
+            .mount(&mock_server)
+            .await;
+        // The external site never sees the headers meant for the site being crawled.
+        Mock::given(header_exists("Authorization"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&external)
+            .await;
+        Mock::given(header_exists("X-Preview-Key"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(0)
+            .mount(&external)
+            .await;
+        Mock::given(method("HEAD"))
+            .and(path("/page"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&external)
+            .await;
+
+        let mut config = Config {
+            check_external_links: true,
+            ..Default::default()
+        };
+        config.client.headers = BTreeMap::from([
+            ("Authorization".to_string(), "Bearer abc".to_string()),
+            ("X-Preview-Key".to_string(), "def".to_string()),
+        ]);
+        let results = Crawler::run_with_sink(seed, config, MemorySink::new())
+            .await
+            .unwrap();
+        let target = Url::parse(&format!("{}/page", external_uri)).unwrap();
+        assert_eq!(
+            results.summary().external_links[&target],
+            Some(StatusCode::OK)
+        );
+    }
+
+    #[tokio::test]
+    async fn recrawl_with_validators() {
+        let mock_server = MockServer::start().await;
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        let landing = format!(r#"<html><body><a href="{}a">a</a></body></html>"#, seed);
+
+        // The landing page supports conditional requests, /a does not.
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .append_header("ETag", "\"v1\"")
+                    .set_body_string(landing.clone()),
+            )
+            .mount(&mock_server)
+            .await;
+        mount_page(&mock_server, "/a", "<p>Before</p>".to_string()).await;
+
+        let results_file = tempfile::NamedTempFile::new().unwrap();
+        let config = Config {
+            save_results: Some(results_file.path().to_path_buf()),
+            ..Default::default()
+        };
+        Crawler::run_with_config(seed.clone(), config)
+            .await
+            .unwrap();
+
+        // /a changes to a copy of the landing page before the next crawl.
+        mock_server.reset().await;
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .and(header("If-None-Match", "\"v1\""))
+            .respond_with(ResponseTemplate::new(304))
+            .mount(&mock_server)
+            .await;
+        mount_page(&mock_server, "/a", landing).await;
+
+        let config = Config {
+            previous_results: Some(results_file.path().to_path_buf()),
+            ..Default::default()
+        };
+        let results = Crawler::run_with_config(seed.clone(), config)
+            .await
+            .unwrap();
+
+        // The landing page wasn't modified, so its links from the previous crawl are followed.
+        let landing = results.get(&seed).unwrap();
+        assert_eq!(landing.changed, Some(false));
+        assert_eq!(landing.links.len(), 1);
+        let a = results.get(&seed.join("a").unwrap()).unwrap();
+        assert_eq!(a.changed, Some(true));
+        // The unmodified landing page still counts when detecting duplicates.
+        assert_eq!(a.duplicate_of, Some(seed));
+    }
+
+    #[tokio::test]
+    async fn recrawl_without_fetching_duplicates() {
+        let mock_server = MockServer::start().await;
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        let landing = r#"<a href="/dup?sort=asc">dup</a>"#.to_string();
+        let duplicate = r#"<link rel="canonical" href="/canonical">"#.to_string();
+        mount_page(&mock_server, "/", landing.clone()).await;
+        mount_page(&mock_server, "/dup", duplicate).await;
+        mount_page(&mock_server, "/canonical", "<p>Canonical</p>".to_string()).await;
+
+        let results_file = tempfile::NamedTempFile::new().unwrap();
+        let config = Config {
+            dedupe_by_canonical: true,
+            save_results: Some(results_file.path().to_path_buf()),
+            ..Default::default()
+        };
+        let results = Crawler::run_with_config(seed.clone(), config)
+            .await
+            .unwrap();
+
+        // The relative canonical URL is resolved against the page, and crawled.
+        let dup = seed.join("/dup?sort=asc").unwrap();
+        let canonical = seed.join("/canonical").unwrap();
+        assert_eq!(
+            results.get(&dup).unwrap().canonical,
+            Some(canonical.clone())
+        );
+        assert!(results.get(&canonical).is_some());
+
+        // The next crawl already knows the duplicate, so only crawls its canonical URL.
+        mock_server.reset().await;
+        mount_page(&mock_server, "/", landing).await;
+        mount_page(&mock_server, "/canonical", "<p>Canonical</p>".to_string()).await;
+        Mock::given(method("GET"))
+            .and(path("/dup"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(0)
+            .mount(&mock_server)
+            .await;
+
+        let config = Config {
+            dedupe_by_canonical: true,
+            previous_results: Some(results_file.path().to_path_buf()),
+            ..Default::default()
+        };
+        let results = Crawler::run_with_config(seed.clone(), config)
+            .await
+            .unwrap();
+        assert_eq!(results.get(&dup).unwrap().changed, None);
+        assert_eq!(results.get(&canonical).unwrap().changed, Some(false));
+    }
+
+    #[tokio::test]
+    async fn crawl_with_cookies_file() {
+        let mock_server = MockServer::start().await;
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        let landing = format!(r#"<html><body><a href="{}a">a</a></body></html>"#, seed);
+
+        // The staging site only serves pages with the session cookie, and sets another.
+        Mock::given(method("GET"))
+            .and(path("/"))
+            .and(header("Cookie", "session=abc"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .append_header("Set-Cookie", "seen=1; Path=/")
+                    .set_body_string(landing),
+            )
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/a"))
+            .and(header_regex("Cookie", "session=abc"))
+            .and(header_regex("Cookie", "seen=1"))
+            .respond_with(ResponseTemplate::new(200))
+            .mount(&mock_server)
+            .await;
+
+        let cookies = tempfile::NamedTempFile::new().unwrap();
+        let host = seed.host_str().unwrap();
+        std::fs::write(
+            cookies.path(),
+            format!("{}\tFALSE\t/\tFALSE\t0\tsession\tabc\n", host),
+        )
+        .unwrap();
+        let config = Config {
+            cookies: Some(cookies.path().to_path_buf()),
+            ..Default::default()
+        };
+        let results = Crawler::run_with_config(seed.clone(), config)
+            .await
+            .unwrap();
+
+        let a = results.get(&seed.join("a").unwrap()).unwrap();
+        assert_eq!(a.status, Some(StatusCode::OK));
+        // The cookie set during the crawl is saved with the original.
+        let saved = std::fs::read_to_string(cookies.path()).unwrap();
+        assert!(saved.contains("\tsession\tabc\n"));
+        assert!(saved.contains("\tseen\t1\n"));
+    }
+
+    #[tokio::test]
+    async fn crawl_after_login() {
+        let mock_server = MockServer::start().await;
+        let seed = Url::parse(&mock_server.uri()).unwrap();
+        Mock::given(method("GET"))
+            .and(path("/login"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"<form method="post" action="/session">
+                    <input type="hidden" name="csrf_token" value="abc123">
+                    <input name="username"><input type="password" name="password">
+                </form>"#,
+            ))
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/session"))
+            .and(body_string("csrf_token=abc123&username=ci&password=s3cret"))
+            .respond_with(ResponseTemplate::new(204).append_header("Set-Cookie", "session=valid"))
+            .mount(&mock_server)
+            .await;
+        let landing = format!(
+            r#"<a href="{0}a">a</a> <a href="{0}account/end">Sign out</a>"#,
+            seed
+        );
+        for (page, body) in [("/", landing), ("/a", String::new())] {
+            Mock::given(method("GET"))
+                .and(path(page))
+                .and(header("Cookie", "session=valid"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(body))
+                .mount(&mock_server)
+                .await;
+        }
+
+        let config = Config {
+            login: Some(LoginConfig {
+                url: seed.join("login").unwrap(),
+                form: None,
+                fields: BTreeMap::from([
+                    ("username".to_string(), "ci".to_string()),
+                    ("password".to_string(), "s3cret".to_string()),
+                ]),
+            }),
+            ..Default::default()
+        };
+        let results = Crawler::run_with_config(seed.clone(), config)
+            .await
+            .unwrap();
+
+        assert_eq!(results.get(&seed).unwrap().status, Some(StatusCode::OK));
+        assert_eq!(
+            results.get(&seed.join("a").unwrap()).unwrap().status,
+            Some(StatusCode::OK)
+        );
+        // The link which logs out is reported but never followed.
+        assert_eq!(results.len(), 2);
+        let requests = mock_server.received_requests().await.unwrap();
+        assert!(!requests
+            .iter()
+            .any(|request| request.url.path() == "/account/end"));
+    }
 }